@@ -47,9 +47,9 @@ fn serialize(bench: &mut Bencher) {
         japan_string: "こんにちは世界".to_string(),
         number_list: vec![1, 2, 3],
         string_list: vec!["1".to_string(), "01".to_string(), "011".to_string()],
-        number_dictionary: number_dictionary,
-        string_dictionary: string_dictionary,
-        list_dictionary: list_dictionary,
+        number_dictionary,
+        string_dictionary,
+        list_dictionary,
         null: Option::None,
     };
     bench.iter(|| {
@@ -0,0 +1,396 @@
+//! Inspects and converts 1code documents from files or stdin.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use serde::de::{Deserialize, IgnoredAny};
+
+use onecode::de::{Event, Tokenizer};
+use onecode::ser::{write_canonical, PrettyPrinter};
+use onecode::{Deserializer, Error};
+
+const USAGE: &str = "\
+Usage: onecode <command> [options] [file]
+
+Reads the document from `file`, or from stdin if it is missing or `-`.
+
+Commands:
+    pretty      Pretty-print the document
+                --indent <n>  spaces per level, 2 by default
+                --width <n>   line width for packed lists, 80 by default
+                --color       colour tokens with ANSI escape codes
+    validate    Check the document and report the location of the first error
+                --strict      accept only the canonical form
+    canonical   Write the document in canonical form, keeping numbers exact
+    to-json     Convert the document to JSON, keeping numbers exact
+                --base64      write byte strings that aren't UTF-8 as base64
+    from-json   Convert a JSON document to 1code
+    stats       Print depth, node counts and the largest strings
+
+Every command but `pretty` and `from-json` takes `--pretty` to accept whitespace
+between tokens, as written by `pretty`, which always accepts it.
+";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("onecode: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    let input = match read_input(options.file.as_deref()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("onecode: {}: {}", options.file_name(), error);
+            process::exit(1);
+        }
+    };
+    let stdout = io::stdout();
+    let mut output = io::BufWriter::new(stdout.lock());
+    let result = run(&options, &input, &mut output).and_then(|()| Ok(output.flush()?));
+    if let Err(error) = result {
+        let _ = output.flush();
+        match error.offset() {
+            Some(offset) => {
+                let (line, column) = line_column(&input, offset);
+                eprintln!(
+                    "onecode: {}:{}:{}: {}",
+                    options.file_name(),
+                    line,
+                    column,
+                    error
+                );
+            }
+            None => eprintln!("onecode: {}: {}", options.file_name(), error),
+        }
+        process::exit(1);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Pretty,
+    Validate,
+    Canonical,
+    ToJson,
+    FromJson,
+    Stats,
+}
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    file: Option<String>,
+    printer: PrettyPrinter,
+    strict: bool,
+    pretty: bool,
+    base64: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut args = args.iter();
+        let command = match args.next().map(String::as_str) {
+            Some("pretty") => Command::Pretty,
+            Some("validate") => Command::Validate,
+            Some("canonical") => Command::Canonical,
+            Some("to-json") => Command::ToJson,
+            Some("from-json") => Command::FromJson,
+            Some("stats") => Command::Stats,
+            Some(command) => return Err(format!("unknown command `{}`", command)),
+            None => return Err("missing command".to_owned()),
+        };
+        let mut options = Options {
+            command,
+            file: None,
+            printer: PrettyPrinter::new(),
+            strict: false,
+            pretty: false,
+            base64: false,
+        };
+        while let Some(arg) = args.next() {
+            let pretty = options.command == Command::Pretty;
+            match arg.as_str() {
+                "--indent" if pretty => {
+                    options.printer = options.printer.indent(number(arg, args.next())?);
+                }
+                "--width" if pretty => {
+                    options.printer = options.printer.width(number(arg, args.next())?);
+                }
+                "--color" if pretty => options.printer = options.printer.color(true),
+                "--strict" if options.command == Command::Validate => options.strict = true,
+                "--pretty" if !pretty && options.command != Command::FromJson => {
+                    options.pretty = true;
+                }
+                "--base64" if options.command == Command::ToJson => options.base64 = true,
+                "-" => options.file = None,
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if options.file.is_some() => return Err(format!("unexpected `{}`", arg)),
+                _ => options.file = Some(arg.clone()),
+            }
+        }
+        Ok(options)
+    }
+
+    fn file_name(&self) -> &str {
+        self.file.as_deref().unwrap_or("<stdin>")
+    }
+}
+
+fn number(option: &str, value: Option<&String>) -> Result<usize, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("`{}` needs a number", option))
+}
+
+fn read_input(file: Option<&str>) -> io::Result<Vec<u8>> {
+    match file {
+        Some(file) => fs::read(file),
+        None => {
+            let mut input = Vec::new();
+            io::stdin().read_to_end(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+fn run<W: Write>(options: &Options, input: &[u8], output: &mut W) -> Result<(), Error> {
+    match options.command {
+        Command::Pretty => {
+            let mut tokenizer = Tokenizer::from_slice(input).whitespace(true);
+            options.printer.write(&mut tokenizer, &mut *output)?;
+            tokenizer.end()?;
+            writeln!(output)?;
+        }
+        Command::Validate => {
+            let mut deserializer = Deserializer::from_slice(input)
+                .strict(options.strict)
+                .whitespace(options.pretty);
+            IgnoredAny::deserialize(&mut deserializer)?;
+            deserializer.end()?;
+            writeln!(output, "ok")?;
+        }
+        Command::Canonical => {
+            let mut tokenizer = Tokenizer::from_slice(input).whitespace(options.pretty);
+            write_canonical(&mut tokenizer, &mut *output)?;
+            tokenizer.end()?;
+        }
+        Command::ToJson => to_json(options, input, output)?,
+        Command::FromJson => from_json(input, output)?,
+        Command::Stats => stats(options, input, output)?,
+    }
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn to_json<W: Write>(options: &Options, input: &[u8], output: &mut W) -> Result<(), Error> {
+    use onecode::json::{ByteStrings, Transcoder};
+
+    let byte_strings = if options.base64 {
+        ByteStrings::Base64
+    } else {
+        ByteStrings::Error
+    };
+    let transcoder = Transcoder::new().byte_strings(byte_strings).pretty(true);
+    let mut deserializer = Deserializer::from_slice(input).whitespace(options.pretty);
+    transcoder.to_json(&mut deserializer, &mut *output)?;
+    deserializer.end()?;
+    writeln!(output)?;
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn from_json<W: Write>(input: &[u8], output: &mut W) -> Result<(), Error> {
+    onecode::json::from_json(input, output)
+}
+
+#[cfg(not(feature = "json"))]
+fn to_json<W: Write>(_options: &Options, _input: &[u8], _output: &mut W) -> Result<(), Error> {
+    Err(no_json())
+}
+
+#[cfg(not(feature = "json"))]
+fn from_json<W: Write>(_input: &[u8], _output: &mut W) -> Result<(), Error> {
+    Err(no_json())
+}
+
+#[cfg(not(feature = "json"))]
+fn no_json() -> Error {
+    serde::de::Error::custom("JSON conversion needs the `json` feature")
+}
+
+/// Number of strings listed by `stats`.
+const LARGEST_STRINGS: usize = 5;
+
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    depth: usize,
+    dictionaries: usize,
+    lists: usize,
+    strings: usize,
+    integers: usize,
+    booleans: usize,
+    nulls: usize,
+    /// Length and offset of the largest strings, largest first.
+    largest: Vec<(usize, usize)>,
+}
+
+impl Stats {
+    fn collect(input: &[u8], whitespace: bool) -> Result<Self, Error> {
+        let mut stats = Stats::default();
+        let mut tokenizer = Tokenizer::from_slice(input).whitespace(whitespace);
+        while let Some(token) = tokenizer.next_token()? {
+            match token.event {
+                Event::DictStart => stats.dictionaries += 1,
+                Event::ListStart => stats.lists += 1,
+                Event::End => {}
+                Event::Int(_) => stats.integers += 1,
+                Event::Bool(_) => stats.booleans += 1,
+                Event::Null => stats.nulls += 1,
+                Event::Str(bytes) => {
+                    stats.strings += 1;
+                    stats.largest.push((bytes.len(), token.offset));
+                    stats
+                        .largest
+                        .sort_by_key(|&(length, _)| std::cmp::Reverse(length));
+                    stats.largest.truncate(LARGEST_STRINGS);
+                }
+            }
+            stats.depth = stats.depth.max(tokenizer.depth());
+        }
+        tokenizer.end()?;
+        Ok(stats)
+    }
+}
+
+fn stats<W: Write>(options: &Options, input: &[u8], output: &mut W) -> Result<(), Error> {
+    let stats = Stats::collect(input, options.pretty)?;
+    writeln!(output, "bytes         {}", input.len())?;
+    writeln!(output, "depth         {}", stats.depth)?;
+    writeln!(output, "dictionaries  {}", stats.dictionaries)?;
+    writeln!(output, "lists         {}", stats.lists)?;
+    writeln!(output, "strings       {}", stats.strings)?;
+    writeln!(output, "integers      {}", stats.integers)?;
+    writeln!(output, "booleans      {}", stats.booleans)?;
+    writeln!(output, "nulls         {}", stats.nulls)?;
+    for (length, offset) in &stats.largest {
+        let (line, column) = line_column(input, *offset);
+        writeln!(
+            output,
+            "string        {} bytes at {}:{}",
+            length, line, column
+        )?;
+    }
+    Ok(())
+}
+
+/// One-based line and column of the byte at `offset`, columns count bytes.
+fn line_column(input: &[u8], offset: usize) -> (usize, usize) {
+    let before = &input[..offset.min(input.len())];
+    let line = before.iter().filter(|&&ch| ch == b'\n').count() + 1;
+    let line_start = before
+        .iter()
+        .rposition(|&ch| ch == b'\n')
+        .map_or(0, |newline| newline + 1);
+    (line, offset - line_start + 1)
+}
+
+#[test]
+fn test_cli() {
+    fn run_str(args: &str, input: &str) -> Result<String, Error> {
+        let args: Vec<String> = args.split_whitespace().map(str::to_owned).collect();
+        let mut output = Vec::new();
+        run(
+            &Options::parse(&args).unwrap(),
+            input.as_bytes(),
+            &mut output,
+        )?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    let input = "d1:bli1ei2ee1:a3:xyze";
+    assert_eq!(
+        run_str("pretty --width 0", input).unwrap(),
+        "d\n  1:b l\n    i1e\n    i2e\n  e\n  1:a 3:xyz\ne\n"
+    );
+    assert_eq!(run_str("validate", input).unwrap(), "ok\n");
+    let error = run_str("validate --strict", input).unwrap_err();
+    assert_eq!(error.offset(), Some(12));
+    assert_eq!(
+        run_str("canonical", input).unwrap(),
+        "d1:a3:xyz1:bli1ei2eee"
+    );
+
+    let mut output = Vec::new();
+    let options = Options::parse(&["canonical".to_owned()]).unwrap();
+    run(&options, b"d1:ai1e2:\xff\xffi2ee", &mut output).unwrap();
+    assert_eq!(output, b"d1:ai1e2:\xff\xffi2ee");
+    assert_eq!(
+        run_str("canonical", "d1:bi1e1:ai0.1000000000000000000001ee").unwrap(),
+        "d1:ai0.1000000000000000000001e1:bi1ee"
+    );
+
+    let stats = Stats::collect(input.as_bytes(), false).unwrap();
+    assert_eq!(
+        stats,
+        Stats {
+            depth: 2,
+            dictionaries: 1,
+            lists: 1,
+            strings: 3,
+            integers: 2,
+            largest: vec![(3, 15), (1, 1), (1, 12)],
+            ..Stats::default()
+        }
+    );
+    assert!(run_str("stats", input)
+        .unwrap()
+        .contains("string        3 bytes at 1:16"));
+
+    let broken = "d\n  1:a i1e\n  1:b x\ne";
+    assert_eq!(run_str("validate", broken).unwrap_err().offset(), Some(1));
+    let error = run_str("validate --pretty", broken).unwrap_err();
+    assert_eq!(
+        line_column(broken.as_bytes(), error.offset().unwrap()),
+        (3, 7)
+    );
+
+    let pretty = run_str("pretty", input).unwrap();
+    assert_eq!(run_str("validate --pretty", &pretty).unwrap(), "ok\n");
+    assert_eq!(
+        run_str("canonical --pretty", &pretty).unwrap(),
+        "d1:a3:xyz1:bli1ei2eee"
+    );
+    assert_eq!(Stats::collect(pretty.as_bytes(), true).unwrap().strings, 3);
+    assert!(run_str("stats", &pretty).is_err());
+
+    assert!(Options::parse(&["pretty".to_owned(), "--strict".to_owned()]).is_err());
+    assert!(Options::parse(&["print".to_owned()]).is_err());
+    assert!(Options::parse(&["from-json".to_owned(), "--pretty".to_owned()]).is_err());
+
+    #[cfg(feature = "json")]
+    {
+        let json = run_str("to-json", input).unwrap();
+        assert_eq!(
+            json,
+            "{\n  \"b\": [\n    1,\n    2\n  ],\n  \"a\": \"xyz\"\n}\n"
+        );
+        assert_eq!(run_str("from-json", &json).unwrap(), input);
+        assert_eq!(run_str("to-json --pretty", &pretty).unwrap(), json);
+        assert_eq!(
+            run_str(
+                "to-json --base64",
+                "li1.50ei123456789012345678901234567890ee"
+            )
+            .unwrap(),
+            "[\n  1.50,\n  123456789012345678901234567890\n]\n"
+        );
+        assert_eq!(
+            run_str("from-json", "[1.50, 123456789012345678901234567890]").unwrap(),
+            "li1.50ei123456789012345678901234567890ee"
+        );
+    }
+}
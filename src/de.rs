@@ -1,44 +1,265 @@
-use std::ops::{AddAssign, MulAssign, Neg};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::io;
+use std::str::FromStr;
 
 use serde::de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
 use serde::Deserialize;
 
-use crate::error::{Error, Result};
+use crate::dialect::{Dialect, Literals};
+use crate::error::{Error, ErrorKind, Result};
+use crate::number::DECIMAL_TOKEN;
+use crate::read::{self, IoRead, Reference, SliceRead};
+use crate::ser::UnitVariant;
+
+mod token;
+
+pub use self::token::{Event, Token, Tokenizer};
+
+/// Default of `Deserializer::max_depth`.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
+
+pub struct Deserializer<R> {
+    read: R,
+    scratch: Vec<u8>,
+    strict: bool,
+    whitespace: bool,
+    unit_variant: UnitVariant,
+    dialect: Dialect,
+    max_depth: usize,
+    /// Number of lists, dictionaries and enums that are open.
+    depth: usize,
+    /// `deserialize_any` passes the exact digits of floats to `visit_newtype_struct`
+    /// too, as it does for integers beyond 128 bits, for transcoding.
+    pub(crate) exact_numbers: bool,
+}
 
-pub struct Deserializer<'de> {
-    input: &'de str,
+impl<'de, R> Deserializer<R>
+where
+    R: read::Read<'de>,
+{
+    pub fn new(read: R) -> Self {
+        Deserializer {
+            read,
+            scratch: Vec::new(),
+            strict: false,
+            whitespace: false,
+            unit_variant: UnitVariant::default(),
+            dialect: Dialect::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+            exact_numbers: false,
+        }
+    }
+
+    /// In strict mode only the canonical form of every value is accepted, so no
+    /// value has two accepted encodings:
+    /// |        rule         |  rejected   |
+    /// | ------------------- | ----------- |
+    /// | `LeadingZero`       | i007e, 03:a |
+    /// | `NegativeZero`      | i-0e        |
+    /// | `TrailingZero`      | i1.50e      |
+    /// | `KeyMustBeAString`  | di1ei1ee    |
+    /// | `UnsortedKeys`      | d1:bN1:aNe  |
+    /// | `DuplicateKey`      | d1:aN1:aNe  |
+    ///
+    /// `i-0e` is still accepted as a float, because it is how `-0.0` is written.
+    /// Whitespace between tokens is rejected even if `whitespace` is on.
+    /// Unit variants are accepted only in the form chosen by `unit_variant`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Skip whitespace between tokens, to read the output of `ser::PrettyPrinter`.
+    /// Off by default, so a document has no whitespace unless it is asked for.
+    pub fn whitespace(mut self, whitespace: bool) -> Self {
+        self.whitespace = whitespace;
+        self
+    }
+
+    /// The form of unit variants that strict mode accepts, the one that
+    /// `ser::Serializer::unit_variant` writes. Both are accepted otherwise.
+    pub fn unit_variant(mut self, unit_variant: UnitVariant) -> Self {
+        self.unit_variant = unit_variant;
+        self
+    }
+
+    /// `Dialect::Bencode` always reads in strict mode, whatever `strict` says,
+    /// because its sorted string keys and integers without leading zeros need it.
+    /// Its strings are passed to `deserialize_any` as bytes.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Deepest nesting of lists, dictionaries and enums that is read, deeper
+    /// input fails with `ErrorKind::RecursionLimitExceeded` instead of
+    /// overflowing the stack. 128 by default, raise it only together with the
+    /// stack size of the thread.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Checks that the whole input was consumed.
+    pub fn end(&mut self) -> Result<()> {
+        self.skip_whitespace()?;
+        match self.read.peek()? {
+            Some(_) => Err(self.peek_error(ErrorKind::TrailingCharacters, "end of input")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Deserializer<SliceRead<'a>> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Deserializer::from_slice(input.as_bytes())
+    }
+
+    pub fn from_slice(input: &'a [u8]) -> Self {
+        Deserializer::new(SliceRead::new(input))
+    }
 }
 
-impl<'de> Deserializer<'de> {
-    pub fn from_str(input: &'de str) -> Self {
-        Deserializer { input }
+impl<R> Deserializer<IoRead<R>>
+where
+    R: io::Read,
+{
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(IoRead::new(reader))
     }
 }
 
+fn from_trait<'de, R, T>(read: R) -> Result<T>
+where
+    R: read::Read<'de>,
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(read);
+    let t = T::deserialize(&mut deserializer).map_err(|error| error.fix_offset(0))?;
+    deserializer.end()?;
+    Ok(t)
+}
+
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_str(s);
-    let t = T::deserialize(&mut deserializer)?;
-    if deserializer.input.is_empty() {
-        Ok(t)
-    } else {
-        Err(Error::TrailingCharacters)
-    }
+    from_slice(s.as_bytes())
+}
+
+/// Strings aren't required to be UTF-8 until a `str` is requested from them.
+pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_trait(SliceRead::new(v))
 }
 
-impl<'de> Deserializer<'de> {
+/// Bytes are pulled from `reader` on demand, so nothing can be borrowed from it.
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    from_trait(IoRead::new(reader))
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: read::Read<'de>,
+{
+    /// Error about the first character in the input, which isn't consumed yet.
+    fn peek_error(&mut self, kind: ErrorKind, expected: &'static str) -> Error {
+        let found = self.read.peek().unwrap_or(None);
+        Error::syntax(kind, self.read.offset(), expected, found)
+    }
+
+    /// Error about a value at the next token that bencode doesn't have.
+    fn not_bencode(&mut self, name: &'static str) -> Error {
+        let _ = self.skip_whitespace();
+        Error::from(ErrorKind::NotBencode(name)).fix_offset(self.read.offset())
+    }
+
+    /// Accounts for a list, dictionary or enum that starts at the next token.
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.max_depth {
+            let _ = self.skip_whitespace();
+            return Err(
+                Error::from(ErrorKind::RecursionLimitExceeded(self.max_depth))
+                    .fix_offset(self.read.offset()),
+            );
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Accounts for the end of what `enter` was called for.
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Runs `f` one level deeper, and leaves that level whether `f` fails or not.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.enter()?;
+        let result = f(self);
+        self.leave();
+        result
+    }
+
     /// Look at the first character in the input without consuming it.
-    fn peek_char(&mut self) -> Result<char> {
-        self.input.chars().next().ok_or(Error::Eof)
+    fn peek_char(&mut self) -> Result<u8> {
+        match self.read.peek()? {
+            Some(ch) => Ok(ch),
+            None => Err(self.peek_error(ErrorKind::Eof, "more input")),
+        }
+    }
+
+    /// Strict mode is on for `Dialect::Bencode` too.
+    fn is_strict(&self) -> bool {
+        self.strict || self.dialect != Dialect::Onecode
+    }
+
+    /// Skip whitespace in front of a token, if `whitespace` is on and not in
+    /// strict mode.
+    fn skip_whitespace(&mut self) -> Result<()> {
+        if self.whitespace && !self.is_strict() {
+            while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.read.peek()? {
+                self.read.next()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Look at the first character of the next token, after any whitespace.
+    fn peek_token(&mut self) -> Result<u8> {
+        self.skip_whitespace()?;
+        self.peek_char()
     }
 
     /// Consume the first character in the input.
-    fn next_char(&mut self) -> Result<char> {
-        let ch = self.peek_char()?;
-        self.input = &self.input[ch.len_utf8()..];
-        Ok(ch)
+    fn next_char(&mut self) -> Result<u8> {
+        match self.read.next()? {
+            Some(ch) => Ok(ch),
+            None => Err(self.peek_error(ErrorKind::Eof, "more input")),
+        }
+    }
+
+    /// Consume the token `expected_ch`, which is a single character.
+    fn expect_char(
+        &mut self,
+        expected_ch: u8,
+        kind: ErrorKind,
+        expected: &'static str,
+    ) -> Result<()> {
+        if self.peek_token()? == expected_ch {
+            self.next_char()?;
+            Ok(())
+        } else {
+            Err(self.peek_error(kind, expected))
+        }
     }
 
     /// | rust  | 1coded |
@@ -46,14 +267,33 @@ impl<'de> Deserializer<'de> {
     /// | true  |   T    |
     /// | false |   F    |
     fn parse_bool(&mut self) -> Result<bool> {
-        if self.input.starts_with("T") {
-            self.input = &self.input["T".len()..];
-            Ok(true)
-        } else if self.input.starts_with("F") {
-            self.input = &self.input["F".len()..];
-            Ok(false)
-        } else {
-            Err(Error::ExpectedBoolean)
+        match self.dialect {
+            Dialect::Onecode => {}
+            Dialect::Bencode(Literals::Integers) => {
+                let start = self.read.offset();
+                return match self.parse_unsigned::<u8>() {
+                    Ok(0) => Ok(false),
+                    Ok(1) => Ok(true),
+                    _ => Err(Error::syntax(
+                        ErrorKind::ExpectedBoolean,
+                        start,
+                        "`i0e` or `i1e`",
+                        Some(b'i'),
+                    )),
+                };
+            }
+            Dialect::Bencode(Literals::Reject) => return Err(self.not_bencode("boolean")),
+        }
+        match self.peek_token()? {
+            b'T' => {
+                self.next_char()?;
+                Ok(true)
+            }
+            b'F' => {
+                self.next_char()?;
+                Ok(false)
+            }
+            _ => Err(self.peek_error(ErrorKind::ExpectedBoolean, "`T` or `F`")),
         }
     }
 
@@ -66,66 +306,151 @@ impl<'de> Deserializer<'de> {
     /// | 1.5   | i1.5e  |
     fn parse_unsigned<T>(&mut self) -> Result<T>
     where
-        T: AddAssign<T> + MulAssign<T> + From<u8>,
+        T: TryFrom<u128>,
     {
-        if self.next_char()? != 'i' {
-            return Err(Error::ExpectedInteger);
+        self.expect_char(b'i', ErrorKind::ExpectedInteger, "`i`")?;
+        self.parse_unsigned_digits()
+    }
+
+    /// The rest of `parse_unsigned` after the opening `i`.
+    fn parse_unsigned_digits<T>(&mut self) -> Result<T>
+    where
+        T: TryFrom<u128>,
+    {
+        let start = self.read.offset();
+        let (_, magnitude) = self.parse_magnitude(false)?;
+        magnitude
+            .and_then(|magnitude| T::try_from(magnitude).ok())
+            .ok_or_else(|| integer_overflow::<T>(start))
+    }
+
+    /// Parse a possible minus sign in front of an integer, every value from
+    /// `T::MIN` to `T::MAX` is accepted.
+    fn parse_signed<T>(&mut self) -> Result<T>
+    where
+        T: TryFrom<i128>,
+    {
+        self.expect_char(b'i', ErrorKind::ExpectedInteger, "`i`")?;
+        self.parse_signed_digits()
+    }
+
+    /// The rest of `parse_signed` after the opening `i`.
+    fn parse_signed_digits<T>(&mut self) -> Result<T>
+    where
+        T: TryFrom<i128>,
+    {
+        let start = self.read.offset();
+        let (negative, magnitude) = self.parse_magnitude(true)?;
+        signed_int(negative, magnitude)
+            .and_then(|int| T::try_from(int).ok())
+            .ok_or_else(|| integer_overflow::<T>(start))
+    }
+
+    /// Digits of an integer up to and including the closing `e`, as a sign and
+    /// a magnitude, `None` if the magnitude doesn't fit into `u128`.
+    fn parse_magnitude(&mut self, signed: bool) -> Result<(bool, Option<u128>)> {
+        let start = self.read.offset();
+        let mut negative = false;
+        if signed && self.peek_char()? == b'-' {
+            negative = true;
+            self.next_char()?;
         }
-        let mut int = T::from(0);
+        let mut magnitude = Some(0_u128);
+        let mut digits = 0;
+        let mut leading_zero = false;
         loop {
-            match self.input.chars().next() {
-                Some(ch @ '0'..='9') => {
-                    self.input = &self.input[1..];
-                    int *= T::from(10);
-                    int += T::from(ch as u8 - b'0');
+            match self.peek_char()? {
+                ch @ b'0'..=b'9' => {
+                    if leading_zero && self.is_strict() {
+                        return Err(self.peek_error(ErrorKind::LeadingZero, "`e` after `0`"));
+                    }
+                    self.next_char()?;
+                    leading_zero = digits == 0 && ch == b'0';
+                    digits += 1;
+                    magnitude = magnitude
+                        .and_then(|magnitude| magnitude.checked_mul(10))
+                        .and_then(|magnitude| magnitude.checked_add(u128::from(ch - b'0')));
                 }
-                Some('e') => {
-                    self.input = &self.input[1..];
-                    return Ok(int);
+                b'e' if digits > 0 => {
+                    if negative && leading_zero && self.is_strict() {
+                        return Err(Error::syntax(
+                            ErrorKind::NegativeZero,
+                            start,
+                            "`0` without a sign",
+                            Some(b'-'),
+                        ));
+                    }
+                    self.next_char()?;
+                    return Ok((negative, magnitude));
                 }
                 _ => {
-                    return Err(Error::ExpectedInteger);
+                    return Err(self.peek_error(ErrorKind::ExpectedInteger, digits_or_end(digits)));
                 }
             }
         }
     }
 
-    /// Parse a possible minus sign in front of an integer.
-    /// Because in `1code` we have `-` sign between `i` and `e` it's easier to copy
-    /// `parse_unsigned`.
-    fn parse_signed<T>(&mut self) -> Result<T>
+    /// Floats are written as plain decimals, an exponent is not allowed.
+    /// | rust  | 1coded  |
+    /// | ----- | ------- |
+    /// | 0     | i0e     |
+    /// | -1    | i-1e    |
+    /// | 1.5   | i1.5e   |
+    /// | -0.25 | i-0.25e |
+    fn parse_float<T>(&mut self) -> Result<T>
     where
-        T: Neg<Output = T> + AddAssign<T> + MulAssign<T> + From<i8>,
+        T: FromStr + Copy + Into<f64>,
     {
-        if self.next_char()? != 'i' {
-            return Err(Error::ExpectedInteger);
-        }
-        let mut negative = false;
-        if self.peek_char()? == '-' {
-            negative = true;
-            self.input = &self.input[1..];
-        }
-        let mut int = T::from(0);
+        let float = parse_finite(self.parse_decimal()?);
+        float.map_err(|error| error.fix_offset(self.number_offset()))
+    }
+
+    /// Offset of the digits that `parse_decimal` just read, right before its `e`.
+    fn number_offset(&self) -> usize {
+        self.read.offset() - self.scratch.len() - 1
+    }
+
+    /// The exact text of a number, for `parse_float`, `deserialize_any` and `Decimal`.
+    fn parse_decimal(&mut self) -> Result<&str> {
+        self.expect_char(b'i', ErrorKind::ExpectedFloat, "`i`")?;
+        let start = self.read.offset();
+        self.scratch.clear();
         loop {
-            match self.input.chars().next() {
-                Some(ch @ '0'..='9') => {
-                    self.input = &self.input[1..];
-                    int *= T::from(10);
-                    int += T::from(ch as i8 - b'0' as i8);
-                }
-                Some('e') => {
-                    self.input = &self.input[1..];
-                    if negative {
-                        return Ok(int.neg());
-                    } else {
-                        return Ok(int);
-                    }
+            match self.peek_char()? {
+                ch @ (b'0'..=b'9' | b'-' | b'.') => self.scratch.push(ch),
+                b'e' => {
+                    self.next_char()?;
+                    break;
                 }
                 _ => {
-                    return Err(Error::ExpectedInteger);
+                    return Err(self.peek_error(ErrorKind::ExpectedFloat, "digit, `-`, `.` or `e`"));
                 }
             }
+            self.next_char()?;
+        }
+        // Only ASCII digits, `-` and `.` were taken, so `number` is valid UTF-8.
+        let number = std::str::from_utf8(&self.scratch).unwrap_or_default();
+        if !is_decimal(number) {
+            let found = self.scratch.first().copied().or(Some(b'e'));
+            return Err(Error::syntax(
+                ErrorKind::ExpectedFloat,
+                start,
+                "decimal number",
+                found,
+            ));
         }
+        if self.is_strict() {
+            check_canonical_decimal(number).map_err(|error| error.fix_offset(start))?;
+        }
+        if self.dialect != Dialect::Onecode {
+            if number.contains('.') {
+                return Err(Error::from(ErrorKind::NotBencode("fraction")).fix_offset(start));
+            }
+            if number == "-0" {
+                return Err(Error::from(ErrorKind::NegativeZero).fix_offset(start));
+            }
+        }
+        Ok(number)
     }
 
     /// Escape symblos are not supported.
@@ -134,63 +459,138 @@ impl<'de> Deserializer<'de> {
     /// | ""    | 0:     |
     /// | "0.1" | 3:0.1  |
     /// | "h h" | 3:h h  |
-    fn parse_string(&mut self) -> Result<&'de str> {
-        //TODO: rewrite to a logic - check that first char is int
-        //self.input.find(':')
-        //use result as length of length's int
-        //parse length - take string
-        let mut length = match self.next_char()? {
-            ch @ '0'..='9' => usize::from(ch as u8 - b'0'),
+    fn parse_string<'s>(&'s mut self) -> Result<Reference<'de, 's, str>> {
+        let start = self.read.offset();
+        match self.parse_bytes()? {
+            Reference::Borrowed(bytes) => std::str::from_utf8(bytes).map(Reference::Borrowed),
+            Reference::Copied(bytes) => std::str::from_utf8(bytes).map(Reference::Copied),
+        }
+        .map_err(|_| Error::from(ErrorKind::InvalidUtf8).fix_offset(start))
+    }
+
+    /// Byte strings share the string form: length in bytes, `:` and raw bytes.
+    /// |  rust  | 1coded |
+    /// | ------ | ------ |
+    /// | b""    | 0:     |
+    /// | b"abc" | 3:abc  |
+    fn parse_bytes<'s>(&'s mut self) -> Result<Reference<'de, 's, [u8]>> {
+        let length = self.parse_length()?;
+        let start = self.read.offset();
+        self.read
+            .read_bytes(length, &mut self.scratch)
+            .map_err(|error| error.fix_offset(start))
+    }
+
+    /// Length prefix of a string up to and including the `:`.
+    fn parse_length(&mut self) -> Result<usize> {
+        let mut length = match self.peek_token()? {
+            ch @ b'0'..=b'9' => usize::from(ch - b'0'),
             _ => {
-                return Err(Error::ExpectedString);
+                return Err(self.peek_error(ErrorKind::ExpectedString, "string length"));
             }
         };
+        self.next_char()?;
+        let leading_zero = length == 0;
         loop {
-            match self.input.chars().next() {
-                Some(ch @ '0'..='9') => {
-                    self.input = &self.input[1..];
-                    length *= usize::from(10 as u8);
-                    length += usize::from(ch as u8 - b'0');
+            match self.peek_char()? {
+                ch @ b'0'..=b'9' => {
+                    if leading_zero && self.is_strict() {
+                        return Err(self.peek_error(ErrorKind::LeadingZero, "`:` after `0`"));
+                    }
+                    // A length that doesn't fit into `usize` can't fit into the input either.
+                    length = match length
+                        .checked_mul(10)
+                        .and_then(|length| length.checked_add(usize::from(ch - b'0')))
+                    {
+                        Some(length) => length,
+                        None => return Err(self.peek_error(ErrorKind::Eof, "shorter length")),
+                    };
                 }
-                Some(':') => {
-                    self.input = &self.input[1..];
+                b':' => {
+                    self.next_char()?;
                     break;
                 }
                 _ => {
-                    return Err(Error::ExpectedString);
+                    return Err(self.peek_error(ErrorKind::ExpectedString, "digit or `:`"));
                 }
             }
+            self.next_char()?;
         }
-        let string = &self.input[..length];
-        self.input = &self.input[length..];
-        return Ok(string);
+        Ok(length)
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de, R> de::Deserializer<'de> for &mut Deserializer<R>
+where
+    R: read::Read<'de>,
+{
     type Error = Error;
 
     /// Because `1code` is self-describing format we can support `deserialize_any`.
+    /// Every token gets the narrowest visitor call that holds it:
+    /// |    1coded     |                 visitor                 |
+    /// | ------------- | --------------------------------------- |
+    /// | N             | visit_unit                              |
+    /// | T, F          | visit_bool                              |
+    /// | 1:a, 1:\xff   | visit_str, or visit_bytes if not UTF-8  |
+    /// | i1e, i-1e     | visit_u64, visit_i64                    |
+    /// | i1…e, i-1…e   | visit_u128, visit_i128, then `BigInt`   |
+    /// | i1.5e, i-0e   | visit_f64                               |
+    /// | l…e, d…e      | visit_seq, visit_map                    |
+    ///
+    /// `i1e` is an integer, even if it was written for `1.0_f64`. In bencode `N`,
+    /// `T` and `F` aren't values and every string goes to `visit_bytes`.
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self.peek_char()? {
-            'N' => self.deserialize_unit(visitor),
-            't' | 'f' => self.deserialize_bool(visitor),
-            '0'..='9' => self.deserialize_str(visitor),
-            'i' => {
-                let mut iterator = self.input.chars();
-                let _first_element = iterator.next().ok_or(Error::Eof)?;
-                match iterator.next().ok_or(Error::Eof)? {
-                    '-' => self.deserialize_i64(visitor),
-                    '0'..='9' => self.deserialize_u64(visitor),
-                    _ => Err(Error::Syntax),
+        match self.peek_token()? {
+            b'N' | b'T' | b'F' if self.dialect != Dialect::Onecode => {
+                Err(self.peek_error(ErrorKind::Syntax, "value"))
+            }
+            b'N' => self.deserialize_unit(visitor),
+            b'T' | b'F' => self.deserialize_bool(visitor),
+            b'0'..=b'9' if self.dialect != Dialect::Onecode => self.deserialize_bytes(visitor),
+            b'0'..=b'9' => match self.parse_bytes()? {
+                Reference::Borrowed(bytes) => match std::str::from_utf8(bytes) {
+                    Ok(string) => visitor.visit_borrowed_str(string),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                },
+                Reference::Copied(bytes) => match std::str::from_utf8(bytes) {
+                    Ok(string) => visitor.visit_str(string),
+                    Err(_) => visitor.visit_bytes(bytes),
+                },
+            },
+            b'i' => {
+                // The narrowest type wins: a fraction or `-0` makes a float, and
+                // integers beyond 128 bits go to `BigInt`.
+                let exact_numbers = self.exact_numbers;
+                let number = self.parse_decimal()?;
+                if number.contains('.') || number == "-0" {
+                    if exact_numbers {
+                        return visitor.visit_newtype_struct(
+                            de::value::StrDeserializer::<Error>::new(number),
+                        );
+                    }
+                    let float = parse_finite(number);
+                    let float = float.map_err(|error| error.fix_offset(self.number_offset()))?;
+                    return visitor.visit_f64(float);
+                }
+                if let Ok(int) = number.parse() {
+                    visitor.visit_u64(int)
+                } else if let Ok(int) = number.parse() {
+                    visitor.visit_i64(int)
+                } else if let Ok(int) = number.parse() {
+                    visitor.visit_u128(int)
+                } else if let Ok(int) = number.parse() {
+                    visitor.visit_i128(int)
+                } else {
+                    visitor.visit_newtype_struct(de::value::StrDeserializer::<Error>::new(number))
                 }
             }
-            'l' => self.deserialize_seq(visitor),
-            'd' => self.deserialize_map(visitor),
-            _ => Err(Error::Syntax),
+            b'l' => self.deserialize_seq(visitor),
+            b'd' => self.deserialize_map(visitor),
+            _ => Err(self.peek_error(ErrorKind::Syntax, "value")),
         }
     }
 
@@ -229,6 +629,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_i64(self.parse_signed()?)
     }
 
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_signed()?)
+    }
+
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
@@ -257,34 +664,37 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_u64(self.parse_unsigned()?)
     }
 
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse_unsigned()?)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_f32(self.parse_float()?)
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_f64(self.parse_float()?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        let start = self.read.offset();
         let string = self.parse_string()?;
-        if string.len() != 1 {
-            return Err(Error::Syntax);
-        } else {
-            visitor.visit_char(
-                string
-                    .chars()
-                    .next()
-                    .expect("Next char should be presented."),
-            )
+        let mut chars = string.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => visitor.visit_char(ch),
+            _ => Err(Error::from(ErrorKind::Syntax).fix_offset(start)),
         }
     }
 
@@ -292,7 +702,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.parse_string()?)
+        match self.parse_string()? {
+            Reference::Borrowed(string) => visitor.visit_borrowed_str(string),
+            Reference::Copied(string) => visitor.visit_str(string),
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -302,26 +715,33 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_str(visitor)
     }
 
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        match self.parse_bytes()? {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_bytes(visitor)
     }
 
+    /// Bencode has no null, so a value that is there is never `None`.
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        if self.input.starts_with("N") {
-            self.input = &self.input["N".len()..];
+        if self.dialect != Dialect::Onecode {
+            return visitor.visit_some(self);
+        }
+        if self.peek_token()? == b'N' {
+            self.next_char()?;
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -332,12 +752,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if self.input.starts_with("N") {
-            self.input = &self.input["N".len()..];
-            visitor.visit_unit()
-        } else {
-            Err(Error::ExpectedNull)
+        if self.dialect != Dialect::Onecode {
+            return Err(self.not_bencode("null"));
         }
+        self.expect_char(b'N', ErrorKind::ExpectedNull, "`N`")?;
+        visitor.visit_unit()
     }
 
     fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
@@ -347,27 +766,28 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    /// `Decimal` gets the exact text of the number.
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == DECIMAL_TOKEN {
+            let digits = self.parse_decimal()?;
+            return visitor.visit_newtype_struct(de::value::StrDeserializer::<Error>::new(digits));
+        }
         visitor.visit_newtype_struct(self)
     }
 
-    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        if self.next_char()? == 'l' {
-            let value = visitor.visit_seq(NotSeparated::new(&mut self))?;
-            if self.next_char()? == 'e' {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedListEnd)
-            }
-        } else {
-            Err(Error::ExpectedList)
-        }
+        let value = self.nested(|de| {
+            de.expect_char(b'l', ErrorKind::ExpectedList, "`l`")?;
+            visitor.visit_seq(NotSeparated::new(de))
+        })?;
+        self.expect_char(b'e', ErrorKind::ExpectedListEnd, "`e`")?;
+        Ok(value)
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
@@ -389,20 +809,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_seq(visitor)
     }
 
-    fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        if self.next_char()? == 'd' {
-            let value = visitor.visit_map(NotSeparated::new(&mut self))?;
-            if self.next_char()? == 'e' {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedDictionaryEnd)
-            }
-        } else {
-            Err(Error::ExpectedDictionary)
-        }
+        let value = self.nested(|de| {
+            de.expect_char(b'd', ErrorKind::ExpectedDictionary, "`d`")?;
+            visitor.visit_map(NotSeparated::new(de))
+        })?;
+        self.expect_char(b'e', ErrorKind::ExpectedDictionaryEnd, "`e`")?;
+        Ok(value)
     }
 
     fn deserialize_struct<V>(
@@ -417,6 +833,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_map(visitor)
     }
 
+    /// Both representations of unit variants are accepted, in strict mode only
+    /// the one of `Deserializer::unit_variant`.
+    /// |        rust        |       1coded       |
+    /// | ------------------ | ------------------ |
+    /// | E::Unit            | 4:Unit             |
+    /// | E::Unit            | d4:UnitNe          |
+    /// | E::Newtype(1)      | d7:Newtypei1ee     |
+    /// | E::Tuple(1, 2)     | d5:Tupleli1ei2eee  |
+    /// | E::Struct { a: 1 } | d6:Structd1:ai1eee |
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
@@ -426,15 +851,23 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if self.next_char()? == 'd' {
-            let value = visitor.visit_enum(Enum::new(self))?;
-            if self.next_char()? == 'e' {
+        match self.peek_token()? {
+            b'0'..=b'9' => {
+                if self.is_strict() && self.unit_variant == UnitVariant::Dictionary {
+                    return Err(self.peek_error(ErrorKind::Syntax, "unit variant as `d`"));
+                }
+                let variant = self.parse_string()?;
+                visitor.visit_enum(de::IntoDeserializer::into_deserializer(&*variant))
+            }
+            b'd' => {
+                let value = self.nested(|de| {
+                    de.next_char()?;
+                    visitor.visit_enum(Enum::new(de))
+                })?;
+                self.expect_char(b'e', ErrorKind::ExpectedDictionaryEnd, "`e`")?;
                 Ok(value)
-            } else {
-                Err(Error::ExpectedDictionaryEnd)
             }
-        } else {
-            Err(Error::ExpectedDictionary)
+            _ => Err(self.peek_error(ErrorKind::ExpectedDictionary, "string or `d`")),
         }
     }
 
@@ -453,32 +886,212 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-struct NotSeparated<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+/// Error about an integer at `offset` that doesn't fit into `T`.
+fn integer_overflow<T>(offset: usize) -> Error {
+    Error::from(ErrorKind::IntegerOverflow(std::any::type_name::<T>())).fix_offset(offset)
+}
+
+/// Applies the sign to a magnitude from `parse_magnitude`, `None` if the
+/// result doesn't fit into `i128`.
+fn signed_int(negative: bool, magnitude: Option<u128>) -> Option<i128> {
+    let magnitude = magnitude?;
+    if negative {
+        0_i128.checked_sub_unsigned(magnitude)
+    } else {
+        i128::try_from(magnitude).ok()
+    }
+}
+
+/// What `parse_magnitude` expects after `digits`.
+fn digits_or_end(digits: usize) -> &'static str {
+    if digits == 0 {
+        "digit"
+    } else {
+        "digit or `e`"
+    }
+}
+
+/// Checks that `number` is `-?[0-9]+(.[0-9]+)?`.
+pub(crate) fn is_decimal(number: &str) -> bool {
+    let unsigned = number.strip_prefix('-').unwrap_or(number);
+    let mut parts = unsigned.splitn(2, '.');
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    match (parts.next(), parts.next()) {
+        (Some(integer), None) => is_digits(integer),
+        (Some(integer), Some(fraction)) => is_digits(integer) && is_digits(fraction),
+        _ => false,
+    }
+}
+
+/// The float nearest to a `number` that passed `is_decimal`, `str::parse` is
+/// correctly rounded for both `f32` and `f64`. Numbers too large for `T` fail
+/// with `ErrorKind::NonFiniteFloat` instead of becoming infinite.
+fn parse_finite<T>(number: &str) -> Result<T>
+where
+    T: FromStr + Copy + Into<f64>,
+{
+    let float: T = number.parse().map_err(|_| ErrorKind::ExpectedFloat)?;
+    if !float.into().is_finite() {
+        return Err(ErrorKind::NonFiniteFloat.into());
+    }
+    Ok(float)
+}
+
+/// Strict mode rules for a `number` that passed `is_decimal`.
+fn check_canonical_decimal(number: &str) -> Result<()> {
+    let unsigned = number.strip_prefix('-').unwrap_or(number);
+    let mut parts = unsigned.splitn(2, '.');
+    let integer = parts.next().unwrap_or_default();
+    if integer.len() > 1 && integer.starts_with('0') {
+        return Err(ErrorKind::LeadingZero.into());
+    }
+    if parts.next().is_some_and(|fraction| fraction.ends_with('0')) {
+        return Err(ErrorKind::TrailingZero.into());
+    }
+    Ok(())
+}
+
+/// Deserializer for the raw bytes of a dictionary key.
+struct MapKey<'de, 's> {
+    key: Reference<'de, 's, [u8]>,
+}
+
+impl<'de> de::Deserializer<'de> for MapKey<'de, '_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Reference::Borrowed(bytes) => match std::str::from_utf8(bytes) {
+                Ok(string) => visitor.visit_borrowed_str(string),
+                Err(_) => visitor.visit_borrowed_bytes(bytes),
+            },
+            Reference::Copied(bytes) => match std::str::from_utf8(bytes) {
+                Ok(string) => visitor.visit_str(string),
+                Err(_) => visitor.visit_bytes(bytes),
+            },
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if std::str::from_utf8(&self.key).is_err() {
+            return Err(ErrorKind::InvalidUtf8.into());
+        }
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match std::str::from_utf8(&self.key) {
+            Ok(variant) => visitor.visit_enum(de::IntoDeserializer::into_deserializer(variant)),
+            Err(_) => Err(ErrorKind::InvalidUtf8.into()),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        unit unit_struct seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+struct NotSeparated<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    /// Index of the next list element, for the error path.
+    index: usize,
+    /// The current dictionary key, for the error path and for the order check in
+    /// strict mode. `None` before the first key and after a non-string key.
+    key: Option<Vec<u8>>,
 }
 
-impl<'a, 'de> NotSeparated<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        NotSeparated { de }
+impl<'a, R: 'a> NotSeparated<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        NotSeparated {
+            de,
+            index: 0,
+            key: None,
+        }
     }
 }
 
-impl<'de, 'a> SeqAccess<'de> for NotSeparated<'a, 'de> {
+impl<'de, 'a, R: read::Read<'de> + 'a> SeqAccess<'de> for NotSeparated<'a, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: DeserializeSeed<'de>,
     {
-        if self.de.peek_char()? == 'e' {
+        if self.de.peek_token()? == b'e' {
             return Ok(None);
-        } else {
-            seed.deserialize(&mut *self.de).map(Some)
         }
+        let offset = self.de.read.offset();
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(&mut *self.de).map(Some).map_err(|error| {
+            error
+                .fix_offset(offset)
+                .prepend_path(format_args!("[{}]", index))
+        })
     }
 }
 
-impl<'de, 'a> MapAccess<'de> for NotSeparated<'a, 'de> {
+impl<'de, 'a, R: read::Read<'de> + 'a> MapAccess<'de> for NotSeparated<'a, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -489,31 +1102,73 @@ impl<'de, 'a> MapAccess<'de> for NotSeparated<'a, 'de> {
         //Maybe we can hold a counter inside `NotSeparated` and
         //increment it on open chars (`i`, `l`, `d`) while decrement till 0.
         //But maybe serde will parse and consume all previous `e`?
-        if self.de.peek_char()? == 'e' {
+        if self.de.peek_token()? == b'e' {
             return Ok(None);
         }
-        seed.deserialize(&mut *self.de).map(Some)
+        let strict = self.de.is_strict();
+        let offset = self.de.read.offset();
+        if !self.de.peek_char()?.is_ascii_digit() {
+            if strict {
+                return Err(self
+                    .de
+                    .peek_error(ErrorKind::KeyMustBeAString, "string key"));
+            }
+            self.key = None;
+            return seed
+                .deserialize(&mut *self.de)
+                .map(Some)
+                .map_err(|error| error.fix_offset(offset));
+        }
+        let key = self.de.parse_bytes()?;
+        match &mut self.key {
+            Some(last_key) => {
+                if strict {
+                    let kind = match (**last_key).cmp(&*key) {
+                        Ordering::Less => None,
+                        Ordering::Equal => Some(ErrorKind::DuplicateKey),
+                        Ordering::Greater => Some(ErrorKind::UnsortedKeys),
+                    };
+                    if let Some(kind) = kind {
+                        return Err(Error::from(kind).fix_offset(offset));
+                    }
+                }
+                last_key.clear();
+                last_key.extend_from_slice(&key);
+            }
+            None => self.key = Some(key.to_vec()),
+        }
+        seed.deserialize(MapKey { key })
+            .map(Some)
+            .map_err(|error| error.fix_offset(offset))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        self.de.skip_whitespace()?;
+        let offset = self.de.read.offset();
+        seed.deserialize(&mut *self.de).map_err(|error| {
+            let error = error.fix_offset(offset);
+            match &self.key {
+                Some(key) => error.prepend_path(format_args!(".{}", String::from_utf8_lossy(key))),
+                None => error.prepend_path(format_args!(".?")),
+            }
+        })
     }
 }
 
-struct Enum<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct Enum<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
 }
 
-impl<'a, 'de> Enum<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+impl<'a, R: 'a> Enum<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
         Enum { de }
     }
 }
 
-impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
+impl<'de, 'a, R: read::Read<'de> + 'a> EnumAccess<'de> for Enum<'a, R> {
     type Error = Error;
     type Variant = Self;
 
@@ -529,11 +1184,17 @@ impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
     }
 }
 
-impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
+impl<'de, 'a, R: read::Read<'de> + 'a> VariantAccess<'de> for Enum<'a, R> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
-        Err(Error::ExpectedString)
+        if self.de.is_strict() && self.de.unit_variant == UnitVariant::String {
+            self.de.skip_whitespace()?;
+            return Err(self
+                .de
+                .peek_error(ErrorKind::Syntax, "unit variant as a string"));
+        }
+        Deserialize::deserialize(self.de)
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
@@ -562,7 +1223,93 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
 fn test_int() {
     let test_1code = r#"i1e"#;
     let expected = 1;
-    assert_eq!(expected, from_str(test_1code).unwrap());
+    assert_eq!(expected, from_str::<i32>(test_1code).unwrap());
+
+    assert_eq!(u8::MAX, from_str::<u8>("i255e").unwrap());
+    assert_eq!(i8::MIN, from_str::<i8>("i-128e").unwrap());
+    assert_eq!(i64::MIN, from_str::<i64>("i-9223372036854775808e").unwrap());
+    assert_eq!(u64::MAX, from_str::<u64>("i18446744073709551615e").unwrap());
+    let overflows = [
+        ("i256e", from_str::<u8>("i256e").map(drop), "u8"),
+        ("i300e", from_str::<u8>("i300e").map(drop), "u8"),
+        ("i-129e", from_str::<i8>("i-129e").map(drop), "i8"),
+        ("i128e", from_str::<i8>("i128e").map(drop), "i8"),
+        (
+            "i18446744073709551616e",
+            from_str::<u64>("i18446744073709551616e").map(drop),
+            "u64",
+        ),
+        (
+            "i-9223372036854775809e",
+            from_str::<i64>("i-9223372036854775809e").map(drop),
+            "i64",
+        ),
+        (
+            "i999999999999999999999999999999999999999999e",
+            from_str::<u64>("i999999999999999999999999999999999999999999e").map(drop),
+            "u64",
+        ),
+    ];
+    for (input, result, name) in overflows.iter() {
+        let error = result.as_ref().unwrap_err();
+        assert_eq!(&ErrorKind::IntegerOverflow(name), error.kind(), "{}", input);
+        assert_eq!(Some(1), error.offset());
+    }
+    assert_eq!(
+        i128::MIN,
+        from_str::<i128>("i-170141183460469231731687303715884105728e").unwrap()
+    );
+    assert_eq!(
+        u128::MAX,
+        from_str::<u128>("i340282366920938463463374607431768211455e").unwrap()
+    );
+    let error = from_str::<u32>("i-1e").unwrap_err();
+    assert_eq!(&ErrorKind::ExpectedInteger, error.kind());
+    assert_eq!(Some("`-`"), error.found());
+}
+
+#[test]
+fn test_float() {
+    assert_eq!(1.5, from_str::<f64>("i1.5e").unwrap());
+    assert_eq!(-0.25, from_str::<f64>("i-0.25e").unwrap());
+    assert_eq!(42.0, from_str::<f64>("i42e").unwrap());
+    assert_eq!(0.1, from_str::<f64>("i0.1e").unwrap());
+    assert_eq!(0.1_f32, from_str::<f32>("i0.1e").unwrap());
+    assert_eq!(
+        f64::from(0.1_f32),
+        from_str::<f64>("i0.100000001490116119384765625e").unwrap()
+    );
+    assert_eq!(
+        0.1_f32,
+        from_str::<f32>("i0.100000001490116119384765625e").unwrap()
+    );
+    for malformed in &[
+        "i1.e", "i.5e", "i-e", "ie", "i1..5e", "i1-5e", "i1x5e", "1:1", "iinfe",
+    ] {
+        assert_eq!(
+            &ErrorKind::ExpectedFloat,
+            from_str::<f64>(malformed).unwrap_err().kind()
+        );
+    }
+    assert_eq!(&ErrorKind::Eof, from_str::<f64>("i1.5").unwrap_err().kind());
+
+    let huge = format!("li1{}ee", "0".repeat(400));
+    let error = from_str::<Vec<f64>>(&huge).unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::NonFiniteFloat, Some(2))
+    );
+    let error = from_str::<f32>(&format!("i-1{}e", "0".repeat(39))).unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::NonFiniteFloat, Some(1))
+    );
+    assert_eq!(
+        1e38_f32,
+        from_str::<f32>("i100000000000000000000000000000000000000e").unwrap()
+    );
+    let error = from_str::<crate::Value>(&format!("i1{}.5e", "0".repeat(400))).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::NonFiniteFloat);
 }
 
 #[test]
@@ -573,6 +1320,193 @@ fn test_string() {
     assert_eq!(expected, actual);
 }
 
+#[test]
+fn test_bytes() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test<'a> {
+        #[serde(borrow)]
+        borrowed: &'a [u8],
+        owned: serde_bytes::ByteBuf,
+    }
+    let test_1code = r#"d8:borrowed3:abc5:owned0:e"#;
+    let expected = Test {
+        borrowed: b"abc",
+        owned: serde_bytes::ByteBuf::new(),
+    };
+    assert_eq!(expected, from_str(test_1code).unwrap());
+    assert_eq!(
+        &ErrorKind::Eof,
+        from_str::<&[u8]>("4:abc").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_slice() {
+    let test_1code = b"l2:\xff\x002:\xd0\xbfe";
+    let expected = vec![
+        serde_bytes::ByteBuf::from(&b"\xff\x00"[..]),
+        serde_bytes::ByteBuf::from(&b"\xd0\xbf"[..]),
+    ];
+    let actual: Vec<serde_bytes::ByteBuf> = from_slice(test_1code).unwrap();
+    assert_eq!(expected, actual);
+    assert_eq!(
+        &ErrorKind::InvalidUtf8,
+        from_slice::<String>(b"2:\xff\x00").unwrap_err().kind()
+    );
+    assert_eq!("п", from_slice::<String>(b"2:\xd0\xbf").unwrap());
+    assert_eq!('п', from_slice::<char>(b"2:\xd0\xbf").unwrap());
+    // The length ends inside of a multi-byte character.
+    assert_eq!(
+        &ErrorKind::InvalidUtf8,
+        from_str::<String>("1:п").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::Eof,
+        from_slice::<String>(b"5:abc").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::Eof,
+        from_slice::<String>(b"99999999999999999999999:abc")
+            .unwrap_err()
+            .kind()
+    );
+}
+
+#[test]
+fn test_reader() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test {
+        int: u32,
+        seq: Vec<String>,
+        bytes: serde_bytes::ByteBuf,
+        float: f64,
+    }
+
+    let test_1code = "d3:inti1e3:seql1:a0:e5:bytes2:\x00\x015:floati-1.5ee";
+    let expected = Test {
+        int: 1,
+        seq: vec!["a".to_owned(), "".to_owned()],
+        bytes: serde_bytes::ByteBuf::from(vec![0, 1]),
+        float: -1.5,
+    };
+    assert_eq!(expected, from_reader(test_1code.as_bytes()).unwrap());
+    assert_eq!(
+        &ErrorKind::TrailingCharacters,
+        from_reader::<_, u32>(&b"i1ei2e"[..]).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::Eof,
+        from_reader::<_, String>(&b"5:abc"[..]).unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_strict() {
+    use std::collections::HashMap;
+
+    fn strict<'a, T: Deserialize<'a>>(input: &'a str) -> Result<T> {
+        let mut deserializer = Deserializer::from_str(input).strict(true);
+        let t = T::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(t)
+    }
+
+    for malformed in &["ie", "i-e", "i--1e"] {
+        assert_eq!(
+            &ErrorKind::ExpectedInteger,
+            from_str::<i32>(malformed).unwrap_err().kind()
+        );
+    }
+    assert_eq!(
+        &ErrorKind::ExpectedInteger,
+        from_str::<u32>("ie").unwrap_err().kind()
+    );
+    assert_eq!(7, from_str::<u8>("i007e").unwrap());
+    assert_eq!(0, from_str::<i8>("i-0e").unwrap());
+
+    assert_eq!(0, strict::<u8>("i0e").unwrap());
+    assert_eq!(-10, strict::<i8>("i-10e").unwrap());
+    assert_eq!(
+        &ErrorKind::LeadingZero,
+        strict::<u8>("i007e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::LeadingZero,
+        strict::<i8>("i-01e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::LeadingZero,
+        strict::<i8>("i00e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::NegativeZero,
+        strict::<i8>("i-0e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::LeadingZero,
+        strict::<String>("03:abc").unwrap_err().kind()
+    );
+    assert_eq!("", strict::<String>("0:").unwrap());
+    assert_eq!(-0.5, strict::<f64>("i-0.5e").unwrap());
+    assert_eq!(
+        &ErrorKind::LeadingZero,
+        strict::<f64>("i01.5e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::TrailingZero,
+        strict::<f64>("i1.50e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::TrailingZero,
+        strict::<f64>("i1.0e").unwrap_err().kind()
+    );
+
+    let sorted = "d0:N1:aN1:bNe";
+    assert_eq!(3, strict::<HashMap<String, ()>>(sorted).unwrap().len());
+    let unsorted = "d1:bN1:aNe";
+    assert_eq!(2, from_str::<HashMap<String, ()>>(unsorted).unwrap().len());
+    assert_eq!(
+        &ErrorKind::UnsortedKeys,
+        strict::<HashMap<String, ()>>(unsorted).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::DuplicateKey,
+        strict::<HashMap<String, ()>>("d1:aN1:aNe")
+            .unwrap_err()
+            .kind()
+    );
+    assert_eq!(
+        &ErrorKind::KeyMustBeAString,
+        strict::<HashMap<u8, ()>>("di1eNe").unwrap_err().kind()
+    );
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Newtype(u8),
+    }
+    assert_eq!(E::Unit, strict::<E>("4:Unit").unwrap());
+    assert_eq!(E::Newtype(1), strict::<E>("d7:Newtypei1ee").unwrap());
+    let error = strict::<E>("d4:UnitNe").unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::Syntax, Some(7))
+    );
+    let dictionary = |input| {
+        let mut deserializer = Deserializer::from_str(input)
+            .strict(true)
+            .unit_variant(UnitVariant::Dictionary);
+        E::deserialize(&mut deserializer)
+    };
+    assert_eq!(E::Unit, dictionary("d4:UnitNe").unwrap());
+    assert_eq!(E::Newtype(1), dictionary("d7:Newtypei1ee").unwrap());
+    let error = dictionary("4:Unit").unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::Syntax, Some(0))
+    );
+}
+
 #[test]
 fn test_seq() {
     let test_1code = r#"l1:a1:be"#;
@@ -609,8 +1543,6 @@ fn test_struct_with_seq() {
 }
 
 #[test]
-//TODO: should agree on https://github.com/soramitsu/1code-java/issues/26 first
-#[ignore]
 fn test_enum() {
     #[derive(Deserialize, PartialEq, Debug)]
     enum E {
@@ -620,19 +1552,541 @@ fn test_enum() {
         Struct { a: u32 },
     }
 
-    let j = r#""Unit""#;
+    let test_1code = r#"4:Unit"#;
     let expected = E::Unit;
-    assert_eq!(expected, from_str(j).unwrap());
+    assert_eq!(expected, from_str(test_1code).unwrap());
+
+    let test_1code = r#"d4:UnitNe"#;
+    let expected = E::Unit;
+    assert_eq!(expected, from_str(test_1code).unwrap());
 
-    let j = r#"{"Newtype":1}"#;
+    let test_1code = r#"d7:Newtypei1ee"#;
     let expected = E::Newtype(1);
-    assert_eq!(expected, from_str(j).unwrap());
+    assert_eq!(expected, from_str(test_1code).unwrap());
 
-    let j = r#"{"Tuple":[1,2]}"#;
+    let test_1code = r#"d5:Tupleli1ei2eee"#;
     let expected = E::Tuple(1, 2);
-    assert_eq!(expected, from_str(j).unwrap());
+    assert_eq!(expected, from_str(test_1code).unwrap());
 
-    let j = r#"{"Struct":{"a":1}}"#;
+    let test_1code = r#"d6:Structd1:ai1eee"#;
     let expected = E::Struct { a: 1 };
-    assert_eq!(expected, from_str(j).unwrap());
+    assert_eq!(expected, from_str(test_1code).unwrap());
+
+    assert!(from_str::<E>(r#"7:Newtype"#).is_err());
+    assert_eq!(
+        &ErrorKind::ExpectedNull,
+        from_str::<E>(r#"d4:Uniti1ee"#).unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_error_position() {
+    #[derive(Deserialize, Debug)]
+    struct Order {
+        #[allow(dead_code)]
+        price: u32,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Orders {
+        #[allow(dead_code)]
+        orders: Vec<Order>,
+    }
+
+    let test_1code = "d6:ordersld5:pricei1eed5:price1:xeee";
+    let error = from_str::<Orders>(test_1code).unwrap_err();
+    assert_eq!(&ErrorKind::ExpectedInteger, error.kind());
+    assert_eq!(Some(30), error.offset());
+    assert_eq!(".orders[1].price", error.path());
+    assert_eq!(Some("`i`"), error.expected());
+    assert_eq!(Some("`1`"), error.found());
+    assert_eq!(
+        error,
+        from_reader::<_, Orders>(test_1code.as_bytes()).unwrap_err()
+    );
+
+    let error = from_str::<Orders>("d6:ordersld5:pricei1eedeee").unwrap_err();
+    assert_eq!(
+        &ErrorKind::Message("missing field `price`".to_owned()),
+        error.kind()
+    );
+    assert_eq!(Some(22), error.offset());
+    assert_eq!(".orders[1]", error.path());
+
+    let error = from_str::<Vec<u8>>("li1ei2").unwrap_err();
+    assert_eq!(&ErrorKind::Eof, error.kind());
+    assert_eq!(Some(6), error.offset());
+    assert_eq!("[1]", error.path());
+    assert_eq!(Some("end of input"), error.found());
+
+    let error = from_str::<u8>("i1ex").unwrap_err();
+    assert_eq!(&ErrorKind::TrailingCharacters, error.kind());
+    assert_eq!(Some(3), error.offset());
+    assert_eq!("", error.path());
+}
+
+#[test]
+fn test_any() {
+    use std::collections::BTreeMap;
+
+    use crate::Value;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Any {
+        Null(()),
+        Bool(bool),
+        Unsigned(u64),
+        Signed(i64),
+        Float(f64),
+        String(String),
+        List(Vec<Any>),
+    }
+
+    let test_1code = "lNTi0ei-1ei-2.5ei-0e1:al2:пee";
+    let expected = Any::List(vec![
+        Any::Null(()),
+        Any::Bool(true),
+        Any::Unsigned(0),
+        Any::Signed(-1),
+        Any::Float(-2.5),
+        Any::Float(-0.0),
+        Any::String("a".to_owned()),
+        Any::List(vec![Any::String("п".to_owned())]),
+    ]);
+    assert_eq!(expected, from_str(test_1code).unwrap());
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Flattened {
+        id: u32,
+        #[serde(flatten)]
+        rest: BTreeMap<String, Value>,
+    }
+
+    let test_1code = "d2:idi1e5:pricei0.25e4:sizei0e4:tagsl1:aTNee";
+    let flattened: Flattened = from_str(test_1code).unwrap();
+    assert_eq!(flattened.id, 1);
+    assert_eq!(flattened.rest["price"], Value::from(0.25));
+    assert_eq!(flattened.rest["size"], Value::from(0));
+    assert_eq!(flattened.rest["tags"][1], Value::from(true));
+
+    let mut deserializer = Deserializer::from_str("i1.50e").strict(true);
+    assert_eq!(
+        &ErrorKind::TrailingZero,
+        Value::deserialize(&mut deserializer).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::ExpectedFloat,
+        from_str::<Value>("i1..5e").unwrap_err().kind()
+    );
+    assert_eq!(
+        Value::from(u128::from(u64::MAX) + 1),
+        from_str::<Value>("i18446744073709551616e").unwrap()
+    );
+}
+
+#[test]
+fn test_serde_attributes() {
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt::Debug;
+
+    use serde::Serialize;
+
+    use crate::{to_vec, to_vec_canonical, Value};
+
+    fn round_trip<T>(value: &T, expected: &[u8])
+    where
+        T: Serialize + for<'a> Deserialize<'a> + PartialEq + Debug,
+    {
+        let output = to_vec(value).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            String::from_utf8_lossy(expected)
+        );
+        assert_eq!(&from_slice::<T>(&output).unwrap(), value);
+        assert_eq!(&from_reader::<_, T>(&output[..]).unwrap(), value);
+
+        let canonical = to_vec_canonical(value).unwrap();
+        let mut de = Deserializer::from_slice(&canonical).strict(true);
+        assert_eq!(&T::deserialize(&mut de).unwrap(), value);
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Flatten {
+        id: u32,
+        #[serde(flatten)]
+        extra: HashMap<String, Value>,
+    }
+
+    let mut extra = HashMap::new();
+    extra.insert("price".to_owned(), Value::from(-1.5));
+    let flatten = Flatten { id: 1, extra };
+    round_trip(&flatten, b"d2:idi1e5:pricei-1.5ee");
+    let mut extra = HashMap::new();
+    extra.insert("hash".to_owned(), Value::Bytes(vec![0xff]));
+    round_trip(&Flatten { id: 1, extra }, b"d2:idi1e4:hash1:\xffe");
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Inner {
+        a: u8,
+        b: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct FlattenStruct {
+        #[serde(flatten)]
+        inner: Inner,
+        c: Vec<i64>,
+    }
+
+    round_trip(
+        &FlattenStruct {
+            inner: Inner { a: 1, b: None },
+            c: vec![-1],
+        },
+        b"d1:ai1e1:bN1:cli-1eee",
+    );
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Untagged {
+        Unit,
+        Int(i64),
+        Float(f64),
+        String(String),
+        Inner(Inner),
+        List(Vec<Untagged>),
+    }
+
+    round_trip(
+        &Untagged::List(vec![
+            Untagged::Unit,
+            Untagged::Int(-7),
+            Untagged::Float(0.5),
+            Untagged::String("x".to_owned()),
+            Untagged::Inner(Inner {
+                a: 2,
+                b: Some("y".to_owned()),
+            }),
+        ]),
+        b"lNi-7ei0.5e1:xd1:ai2e1:b1:yee",
+    );
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(tag = "type")]
+    enum Internally {
+        Unit,
+        Newtype(Inner),
+        Struct { price: f64, tags: Vec<String> },
+    }
+
+    round_trip(
+        &vec![
+            Internally::Unit,
+            Internally::Newtype(Inner { a: 3, b: None }),
+            Internally::Struct {
+                price: 0.25,
+                tags: vec!["t".to_owned()],
+            },
+        ],
+        b"ld4:type4:United4:type7:Newtype1:ai3e1:bNed4:type6:Struct5:pricei0.25e4:tagsl1:teee",
+    );
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(tag = "t", content = "c")]
+    enum Adjacently {
+        Unit,
+        Newtype(u64),
+        Tuple(u8, bool),
+        Struct { a: u8 },
+    }
+
+    round_trip(
+        &vec![
+            Adjacently::Unit,
+            Adjacently::Newtype(u64::MAX),
+            Adjacently::Tuple(1, false),
+            Adjacently::Struct { a: 4 },
+        ],
+        b"ld1:t4:United1:t7:Newtype1:ci18446744073709551615eed1:t5:Tuple1:cli1eFeed1:t6:Struct1:cd1:ai4eeee",
+    );
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Defaults {
+        #[serde(default)]
+        count: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        meta: BTreeMap<String, u8>,
+    }
+
+    round_trip(
+        &Defaults {
+            count: 0,
+            note: None,
+            tags: vec![],
+            meta: BTreeMap::new(),
+        },
+        b"d5:counti0ee",
+    );
+    round_trip(
+        &Defaults {
+            count: 2,
+            note: Some("n".to_owned()),
+            tags: vec!["t".to_owned()],
+            meta: BTreeMap::new(),
+        },
+        b"d5:counti2e4:note1:n4:tagsl1:tee",
+    );
+    assert_eq!(
+        Defaults {
+            count: 0,
+            note: None,
+            tags: vec![],
+            meta: BTreeMap::new(),
+        },
+        from_str("de").unwrap()
+    );
+}
+
+#[test]
+fn test_whitespace() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Struct { a: Option<u8> },
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test {
+        list: Vec<i32>,
+        enums: (E, E),
+        text: String,
+    }
+
+    fn from_pretty<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T> {
+        let mut de = Deserializer::from_str(input).whitespace(true);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    let input = "\n d\n  4:list l i1e\ti-2e e\r\n  5:enums l 4:Unit d 6:Struct d 1:a N e e e\n  4:text 2:a \ne\n";
+    let expected = Test {
+        list: vec![1, -2],
+        enums: (E::Unit, E::Struct { a: None }),
+        text: "a ".to_owned(),
+    };
+    assert_eq!(expected, from_pretty(input).unwrap());
+    let mut de = Deserializer::from_reader(input.as_bytes()).whitespace(true);
+    assert_eq!(expected, Test::deserialize(&mut de).unwrap());
+    de.end().unwrap();
+    assert_eq!(
+        &ErrorKind::ExpectedInteger,
+        from_pretty::<i32>("i 1e").unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::ExpectedString,
+        from_pretty::<String>("1 :a").unwrap_err().kind()
+    );
+
+    assert!(from_str::<Test>(input).is_err());
+    assert_eq!(
+        &ErrorKind::TrailingCharacters,
+        from_str::<i32>("i1e ").unwrap_err().kind()
+    );
+    let mut de = Deserializer::from_str("l i1e e")
+        .whitespace(true)
+        .strict(true);
+    assert_eq!(
+        &ErrorKind::ExpectedInteger,
+        Vec::<i32>::deserialize(&mut de).unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_bencode() {
+    use crate::ser::Serializer;
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Torrent {
+        announce: String,
+        info: Info,
+        comment: Option<String>,
+        private: bool,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Info {
+        name: String,
+        #[serde(rename = "piece length")]
+        piece_length: u64,
+        pieces: serde_bytes::ByteBuf,
+    }
+
+    fn to_bencode<T: serde::Serialize>(value: &T, literals: Literals) -> Result<Vec<u8>> {
+        let mut ser = Serializer::new(Vec::new()).dialect(Dialect::Bencode(literals));
+        value.serialize(&mut ser)?;
+        Ok(ser.into_inner())
+    }
+
+    fn from_bencode<'de, T: Deserialize<'de>>(input: &'de [u8], literals: Literals) -> Result<T> {
+        let mut de = Deserializer::from_slice(input).dialect(Dialect::Bencode(literals));
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    let torrent = Torrent {
+        announce: "http://tracker/announce".to_owned(),
+        info: Info {
+            name: "a.txt".to_owned(),
+            piece_length: 16384,
+            pieces: serde_bytes::ByteBuf::from(vec![0xff, 0x00, 0x80]),
+        },
+        comment: None,
+        private: true,
+    };
+    let bencoded: &[u8] =
+        b"d8:announce23:http://tracker/announce4:infod4:name5:a.txt12:piece lengthi16384e6:pieces3:\xff\x00\x80e7:privatei1ee";
+    assert_eq!(to_bencode(&torrent, Literals::Integers).unwrap(), bencoded);
+    assert_eq!(
+        from_bencode::<Torrent>(bencoded, Literals::Integers).unwrap(),
+        torrent
+    );
+    assert_eq!(
+        &ErrorKind::NotBencode("null"),
+        to_bencode(&torrent, Literals::Reject).unwrap_err().kind()
+    );
+    let error = from_bencode::<Torrent>(bencoded, Literals::Reject).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::NotBencode("boolean"));
+    assert_eq!((error.path(), error.offset()), (".private", Some(102)));
+
+    let mut map = BTreeMap::new();
+    map.insert("a".to_owned(), Some(1));
+    map.insert("b".to_owned(), None);
+    assert_eq!(to_bencode(&map, Literals::Integers).unwrap(), b"d1:ai1ee");
+    assert_eq!(
+        &ErrorKind::NotBencode("null"),
+        to_bencode(&map, Literals::Reject).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::NotBencode("null"),
+        to_bencode(&vec![None::<u8>], Literals::Integers)
+            .unwrap_err()
+            .kind()
+    );
+    assert_eq!(
+        &ErrorKind::NotBencode("float"),
+        to_bencode(&1.5, Literals::Integers).unwrap_err().kind()
+    );
+
+    let value: crate::Value = from_bencode(b"d1:al1:xi-1eee", Literals::Reject).unwrap();
+    assert_eq!(value["a"][0], crate::Value::Bytes(b"x".to_vec()));
+    assert_eq!(value["a"][1], crate::Value::Int(-1));
+    let error =
+        |input: &'static [u8]| from_bencode::<crate::Value>(input, Literals::Integers).unwrap_err();
+    assert_eq!(&ErrorKind::Syntax, error(b"lTe").kind());
+    assert_eq!(&ErrorKind::NotBencode("fraction"), error(b"i1.5e").kind());
+    assert_eq!(&ErrorKind::NegativeZero, error(b"i-0e").kind());
+    assert_eq!(&ErrorKind::LeadingZero, error(b"i01e").kind());
+    assert_eq!(&ErrorKind::UnsortedKeys, error(b"d1:bi1e1:ai2ee").kind());
+    let not_strict = |input: &'static [u8]| {
+        let mut de = Deserializer::from_slice(input)
+            .dialect(Dialect::Bencode(Literals::Integers))
+            .strict(false);
+        crate::Value::deserialize(&mut de).unwrap_err()
+    };
+    assert_eq!(
+        &ErrorKind::UnsortedKeys,
+        not_strict(b"d1:bi1e1:ai2ee").kind()
+    );
+    assert_eq!(&ErrorKind::LeadingZero, not_strict(b"i01e").kind());
+    assert_eq!(&ErrorKind::LeadingZero, not_strict(b"01:a").kind());
+    assert_eq!(
+        &ErrorKind::ExpectedBoolean,
+        from_bencode::<bool>(b"i2e", Literals::Integers)
+            .unwrap_err()
+            .kind()
+    );
+}
+
+#[test]
+fn test_recursion_limit() {
+    use crate::Value;
+
+    let deep = "l".repeat(100_000);
+    let error = from_str::<Value>(&deep).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::RecursionLimitExceeded(128));
+    assert_eq!(error.offset(), Some(128));
+    assert_eq!(error.path(), &"[0]".repeat(128));
+    let error = from_reader::<_, de::IgnoredAny>(deep.as_bytes()).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::RecursionLimitExceeded(128));
+    let nested = format!("{}{}", "l".repeat(128), "e".repeat(128));
+    assert!(from_str::<Value>(&nested).is_ok());
+
+    fn limited<'a, T: Deserialize<'a>>(input: &'a str, max_depth: usize) -> Result<T> {
+        let mut de = Deserializer::from_str(input).max_depth(max_depth);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    assert!(limited::<Value>("ld1:aleee", 3).is_ok());
+    let error = limited::<Value>("ld1:aleee", 2).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::RecursionLimitExceeded(2));
+    assert_eq!((error.path(), error.offset()), ("[0].a", Some(5)));
+    assert_eq!(
+        error.to_string(),
+        "nesting is deeper than 2 levels at [0].a, offset 5"
+    );
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum E {
+        Newtype(Vec<u8>),
+    }
+    assert_eq!(
+        limited::<E>("d7:Newtypeli1eee", 2).unwrap(),
+        E::Newtype(vec![1])
+    );
+    assert_eq!(
+        &ErrorKind::RecursionLimitExceeded(1),
+        limited::<E>("d7:Newtypeli1eee", 1).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::RecursionLimitExceeded(0),
+        limited::<Vec<u8>>("le", 0).unwrap_err().kind()
+    );
+
+    // Failed lists, dictionaries and enums give their level back.
+    let mut de = Deserializer::from_str("li1e1:ae");
+    assert!(Vec::<u8>::deserialize(&mut de).is_err());
+    assert_eq!(de.depth, 0);
+    let mut de = Deserializer::from_str("d1:a1:be");
+    assert!(std::collections::BTreeMap::<String, u8>::deserialize(&mut de).is_err());
+    assert_eq!(de.depth, 0);
+    let mut de = Deserializer::from_str("d7:Newtype1:ae");
+    assert!(E::deserialize(&mut de).is_err());
+    assert_eq!(de.depth, 0);
+    let mut de = Deserializer::from_str("i1e");
+    assert!(Vec::<u8>::deserialize(&mut de).is_err());
+    assert_eq!(de.depth, 0);
+
+    let mut tokenizer = Tokenizer::from_str(&deep).max_depth(3);
+    for _ in 0..3 {
+        tokenizer.next_token().unwrap();
+    }
+    let error = tokenizer.next_token().unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::RecursionLimitExceeded(3), Some(3))
+    );
+    assert_eq!(
+        &ErrorKind::RecursionLimitExceeded(128),
+        crate::reformat(&deep).unwrap_err().kind()
+    );
 }
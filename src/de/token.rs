@@ -0,0 +1,372 @@
+//! Pull parser that walks a document token by token, without serde or a tree.
+
+use std::cmp::Ordering;
+use std::io;
+
+use super::Deserializer;
+use crate::error::{Error, ErrorKind, Result};
+use crate::read::{self, IoRead, SliceRead};
+
+/// A single token of a document.
+/// |   1coded    |            event             |
+/// | ----------- | ---------------------------- |
+/// | d           | DictStart                    |
+/// | l           | ListStart                    |
+/// | e           | End                          |
+/// | i-1e, i1.5e | Int("-1"), Int("1.5")        |
+/// | 3:abc       | Str(b"abc")                  |
+/// | T, F        | Bool(true), Bool(false)      |
+/// | N           | Null                         |
+///
+/// Dictionary keys are `Str` events in front of their values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a> {
+    DictStart,
+    ListStart,
+    End,
+    /// Exact text of a number, floats and integers of any size included.
+    Int(&'a str),
+    Str(&'a [u8]),
+    Bool(bool),
+    Null,
+}
+
+/// An `Event` together with the byte offset of its first character.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub offset: usize,
+    pub event: Event<'a>,
+}
+
+/// A list or dictionary that isn't closed yet.
+enum Frame {
+    List,
+    Dict {
+        /// Whether the next token is a key rather than a value.
+        key: bool,
+        /// The previous key, for the order check in strict mode.
+        last_key: Option<Vec<u8>>,
+    },
+}
+
+/// Reads one value token by token, checking that lists and dictionaries are
+/// balanced. Numbers and strings are validated the same way as by `Deserializer`,
+/// including its strict mode.
+pub struct Tokenizer<R> {
+    de: Deserializer<R>,
+    stack: Vec<Frame>,
+    done: bool,
+}
+
+impl<'de, R> Tokenizer<R>
+where
+    R: read::Read<'de>,
+{
+    pub fn new(read: R) -> Self {
+        Tokenizer {
+            de: Deserializer::new(read),
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Accept only canonical input, see `Deserializer::strict`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.de.strict = strict;
+        self
+    }
+
+    /// Skip whitespace between tokens, see `Deserializer::whitespace`.
+    pub fn whitespace(mut self, whitespace: bool) -> Self {
+        self.de.whitespace = whitespace;
+        self
+    }
+
+    /// Deepest nesting that is read, see `Deserializer::max_depth`. Tokens don't
+    /// recurse, but consumers such as `PrettyPrinter` do.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.de.max_depth = max_depth;
+        self
+    }
+
+    /// Number of lists and dictionaries that are open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// The next token, `None` once the root value is complete.
+    pub fn next_token<'s>(&'s mut self) -> Result<Option<Token<'s>>>
+    where
+        'de: 's,
+    {
+        self.read_token(false)
+    }
+
+    /// Consume the next value, or key, with everything nested in it. Strings are
+    /// jumped over using their length prefixes instead of being read.
+    pub fn skip_value(&mut self) -> Result<()> {
+        if self.done || self.de.peek_token()? == b'e' {
+            return Err(self.de.peek_error(ErrorKind::Syntax, "value"));
+        }
+        let depth = self.stack.len();
+        while self.read_token(true)?.is_some() && self.stack.len() > depth {}
+        Ok(())
+    }
+
+    /// Checks that the whole input was consumed after `next_token` returned `None`.
+    pub fn end(&mut self) -> Result<()> {
+        self.de.end()
+    }
+
+    /// `skip` jumps over string values, which come out empty.
+    fn read_token<'s>(&'s mut self, skip: bool) -> Result<Option<Token<'s>>>
+    where
+        'de: 's,
+    {
+        if self.done {
+            return Ok(None);
+        }
+        let ch = self.de.peek_token()?;
+        let offset = self.de.read.offset();
+        let is_key = matches!(self.stack.last(), Some(Frame::Dict { key: true, .. }));
+        if is_key && self.de.strict && !ch.is_ascii_digit() && ch != b'e' {
+            return Err(self
+                .de
+                .peek_error(ErrorKind::KeyMustBeAString, "string key"));
+        }
+        let event = match ch {
+            b'e' => {
+                match self.stack.last() {
+                    Some(Frame::List) | Some(Frame::Dict { key: true, .. }) => {}
+                    _ => return Err(self.de.peek_error(ErrorKind::Syntax, "value")),
+                }
+                self.de.next_char()?;
+                self.stack.pop();
+                self.complete();
+                Event::End
+            }
+            b'l' => {
+                self.enter()?;
+                self.de.next_char()?;
+                self.stack.push(Frame::List);
+                Event::ListStart
+            }
+            b'd' => {
+                self.enter()?;
+                self.de.next_char()?;
+                self.stack.push(Frame::Dict {
+                    key: true,
+                    last_key: None,
+                });
+                Event::DictStart
+            }
+            b'T' | b'F' => {
+                let value = self.de.parse_bool()?;
+                self.complete();
+                Event::Bool(value)
+            }
+            b'N' => {
+                self.de.next_char()?;
+                self.complete();
+                Event::Null
+            }
+            b'i' => {
+                let digits = self.de.parse_decimal()?;
+                complete(&mut self.stack, &mut self.done);
+                Event::Int(digits)
+            }
+            b'0'..=b'9' if skip && !is_key => {
+                let length = self.de.parse_length()?;
+                let start = self.de.read.offset();
+                self.de
+                    .read
+                    .skip_bytes(length)
+                    .map_err(|error| error.fix_offset(start))?;
+                self.complete();
+                Event::Str(&[])
+            }
+            b'0'..=b'9' => {
+                let strict = self.de.strict;
+                let bytes = self.de.parse_bytes()?;
+                if let (true, Some(Frame::Dict { last_key, .. })) = (is_key, self.stack.last_mut())
+                {
+                    if let (true, Some(last_key)) = (strict, &last_key) {
+                        let kind = match (**last_key).cmp(&*bytes) {
+                            Ordering::Less => None,
+                            Ordering::Equal => Some(ErrorKind::DuplicateKey),
+                            Ordering::Greater => Some(ErrorKind::UnsortedKeys),
+                        };
+                        if let Some(kind) = kind {
+                            return Err(Error::from(kind).fix_offset(offset));
+                        }
+                    }
+                    let last_key = last_key.get_or_insert_with(Vec::new);
+                    last_key.clear();
+                    last_key.extend_from_slice(&bytes);
+                }
+                complete(&mut self.stack, &mut self.done);
+                Event::Str(match bytes {
+                    read::Reference::Borrowed(bytes) => bytes,
+                    read::Reference::Copied(bytes) => bytes,
+                })
+            }
+            _ => return Err(self.de.peek_error(ErrorKind::Syntax, "value")),
+        };
+        Ok(Some(Token { offset, event }))
+    }
+
+    /// Checks the nesting limit of the `Deserializer` before a list or
+    /// dictionary is opened, the stack itself is on the heap.
+    fn enter(&mut self) -> Result<()> {
+        if self.stack.len() >= self.de.max_depth {
+            return Err(
+                Error::from(ErrorKind::RecursionLimitExceeded(self.de.max_depth))
+                    .fix_offset(self.de.read.offset()),
+            );
+        }
+        Ok(())
+    }
+
+    /// Account for a value that is complete, a key counts as a value.
+    fn complete(&mut self) {
+        complete(&mut self.stack, &mut self.done);
+    }
+}
+
+/// `Tokenizer::complete` on the fields, so it can run while a parsed token still
+/// borrows the `Deserializer`. Called only once the token is parsed, so a failed
+/// token leaves the state as it was.
+fn complete(stack: &mut [Frame], done: &mut bool) {
+    match stack.last_mut() {
+        Some(Frame::Dict { key, .. }) => *key = !*key,
+        Some(Frame::List) => {}
+        None => *done = true,
+    }
+}
+
+impl<'a> Tokenizer<SliceRead<'a>> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Tokenizer::from_slice(input.as_bytes())
+    }
+
+    pub fn from_slice(input: &'a [u8]) -> Self {
+        Tokenizer::new(SliceRead::new(input))
+    }
+}
+
+impl<R> Tokenizer<IoRead<R>>
+where
+    R: io::Read,
+{
+    pub fn from_reader(reader: R) -> Self {
+        Tokenizer::new(IoRead::new(reader))
+    }
+}
+
+#[test]
+fn test_tokenizer() {
+    fn tokens<'de, R: read::Read<'de>>(mut tokenizer: Tokenizer<R>) -> Vec<(usize, String)> {
+        let mut tokens = Vec::new();
+        while let Some(token) = tokenizer.next_token().unwrap() {
+            let event = match token.event {
+                Event::Int(digits) => format!("Int({})", digits),
+                Event::Str(bytes) => format!("Str({})", String::from_utf8_lossy(bytes)),
+                event => format!("{:?}", event),
+            };
+            tokens.push((token.offset, event));
+        }
+        tokenizer.end().unwrap();
+        tokens
+    }
+
+    let input = "d1:ali-1ei1.5eN4:ab\x00de1:bTe";
+    let expected = vec![
+        (0, "DictStart"),
+        (1, "Str(a)"),
+        (4, "ListStart"),
+        (5, "Int(-1)"),
+        (9, "Int(1.5)"),
+        (14, "Null"),
+        (15, "Str(ab\0d)"),
+        (21, "End"),
+        (22, "Str(b)"),
+        (25, "Bool(true)"),
+        (26, "End"),
+    ];
+    let expected: Vec<_> = expected
+        .into_iter()
+        .map(|(offset, event)| (offset, event.to_owned()))
+        .collect();
+    assert_eq!(tokens(Tokenizer::from_str(input)), expected);
+    assert_eq!(tokens(Tokenizer::from_reader(input.as_bytes())), expected);
+
+    let input = "d3:bigl5:xxxxxd1:ali340282366920938463463374607431768211456eeee5:smallFe";
+    let mut tokenizer = Tokenizer::from_reader(input.as_bytes());
+    assert_eq!(
+        tokenizer.next_token().unwrap().unwrap().event,
+        Event::DictStart
+    );
+    tokenizer.skip_value().unwrap();
+    assert_eq!(tokenizer.depth(), 1);
+    tokenizer.skip_value().unwrap();
+    let token = tokenizer.next_token().unwrap().unwrap();
+    assert_eq!((token.offset, token.event), (63, Event::Str(b"small")));
+    assert_eq!(
+        tokenizer.next_token().unwrap().unwrap().event,
+        Event::Bool(false)
+    );
+    assert_eq!(
+        &ErrorKind::Syntax,
+        tokenizer.skip_value().unwrap_err().kind()
+    );
+    assert_eq!(tokenizer.next_token().unwrap().unwrap().event, Event::End);
+    assert_eq!(tokenizer.next_token().unwrap(), None);
+    tokenizer.end().unwrap();
+
+    let mut tokenizer = Tokenizer::from_str("li1eei2e");
+    tokenizer.skip_value().unwrap();
+    assert_eq!(tokenizer.next_token().unwrap(), None);
+    assert_eq!(
+        &ErrorKind::TrailingCharacters,
+        tokenizer.end().unwrap_err().kind()
+    );
+
+    // A token that fails doesn't count as a value, so the root isn't complete.
+    let mut tokenizer = Tokenizer::from_str("i1xe");
+    assert_eq!(
+        &ErrorKind::ExpectedFloat,
+        tokenizer.next_token().unwrap_err().kind()
+    );
+    assert!(tokenizer.next_token().is_err());
+    let mut tokenizer = Tokenizer::from_str("d4:abc");
+    tokenizer.next_token().unwrap();
+    assert_eq!(&ErrorKind::Eof, tokenizer.next_token().unwrap_err().kind());
+    assert_eq!(tokenizer.depth(), 1);
+    assert!(matches!(
+        tokenizer.stack.last(),
+        Some(Frame::Dict { key: true, .. })
+    ));
+
+    fn error(input: &str, strict: bool) -> Error {
+        let mut tokenizer = Tokenizer::from_str(input).strict(strict);
+        loop {
+            match tokenizer.next_token() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("{} is accepted", input),
+                Err(error) => return error,
+            }
+        }
+    }
+
+    assert_eq!(&ErrorKind::Eof, error("li1e", false).kind());
+    assert_eq!(&ErrorKind::Syntax, error("d1:ae", false).kind());
+    assert_eq!(&ErrorKind::Syntax, error("lxe", false).kind());
+    assert_eq!(Some(4), error("d1:ae", false).offset());
+    assert_eq!(tokens(Tokenizer::from_str("di1ei2ee")).len(), 4);
+    assert_eq!(&ErrorKind::KeyMustBeAString, error("di1ei2ee", true).kind());
+    assert_eq!(&ErrorKind::UnsortedKeys, error("d1:bN1:aNe", true).kind());
+    assert_eq!(&ErrorKind::DuplicateKey, error("d1:aN1:aNe", true).kind());
+    assert_eq!(&ErrorKind::LeadingZero, error("i01e", true).kind());
+    assert_eq!(Some(5), error("d1:bN1:aNe", true).offset());
+}
@@ -0,0 +1,37 @@
+//! Wire formats that `Serializer` and `Deserializer` speak.
+
+/// `Bencode` is the format 1code is derived from. It has the same integers,
+/// strings, lists and dictionaries, but no booleans, nulls or fractions:
+/// |  rust   |  1coded  |       bencode        |
+/// | ------- | -------- | -------------------- |
+/// | -1      | i-1e     | i-1e                 |
+/// | b"a"    | 1:a      | 1:a                  |
+/// | 1.5     | i1.5e    | error                |
+/// | true    | T        | see `Literals`       |
+/// | None    | N        | see `Literals`       |
+///
+/// Bencode dictionary keys are strings sorted bytewise, so the `Serializer`
+/// sorts them and the `Deserializer` checks them as in strict mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    #[default]
+    Onecode,
+    Bencode(Literals),
+}
+
+/// What happens to booleans and nulls in bencode.
+/// |     variant     |  rust   |            bencode             |
+/// | --------------- | ------- | ------------------------------ |
+/// | `Reject`        | true    | error                          |
+/// | `Reject`        | None    | error                          |
+/// | `Integers`      | true    | i1e                            |
+/// | `Integers`      | None    | entry left out of a dictionary |
+///
+/// With `Integers` a `None` anywhere else than as a dictionary value still fails,
+/// and a missing entry reads back as `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Literals {
+    #[default]
+    Reject,
+    Integers,
+}
@@ -1,52 +1,267 @@
-use std;
 use std::fmt::{self, Display};
+use std::io;
 
 use serde::{de, ser};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum Error {
+/// What went wrong, together with where it went wrong in the input.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    inner: Box<ErrorImpl>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ErrorImpl {
+    kind: ErrorKind,
+    offset: Option<usize>,
+    path: String,
+    expected: Option<&'static str>,
+    found: Option<String>,
+}
+
+/// Reason of an `Error`. More variants may be added in minor releases.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
     Message(String),
+    /// Reading the input or writing the output failed.
+    Io(io::Error),
 
     Eof,
     Syntax,
     ExpectedNull,
     ExpectedBoolean,
     ExpectedInteger,
+    /// The integer doesn't fit into the named type.
+    IntegerOverflow(&'static str),
+    ExpectedFloat,
+    /// NaN and infinite floats have no 1code form, and numbers too large for
+    /// the float type aren't read as infinities.
+    NonFiniteFloat,
     ExpectedString,
+    InvalidUtf8,
     ExpectedList,
     ExpectedListEnd,
     ExpectedDictionary,
     ExpectedDictionaryEnd,
+    KeyMustBeAString,
+    DuplicateKey,
+    UnsortedKeys,
+    LeadingZero,
+    NegativeZero,
+    TrailingZero,
     TrailingCharacters,
+    /// An `Emitter` call doesn't fit the lists and dictionaries that are open.
+    InvalidNesting,
+    /// The named kind of value, such as a boolean, can't be written in bencode.
+    NotBencode(&'static str),
+    /// Lists, dictionaries and enums are nested deeper than the limit, see
+    /// `Deserializer::max_depth`.
+    RecursionLimitExceeded(usize),
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner.kind
+    }
+
+    /// Byte offset into the input of the character that caused the error, or of
+    /// the value that was rejected by its `Deserialize` implementation.
+    /// `None` for errors that don't come from parsing.
+    pub fn offset(&self) -> Option<usize> {
+        self.inner.offset
+    }
+
+    /// Path from the root to the failed value, such as `.orders[12].price`.
+    /// Empty when the error is about the root value itself.
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    /// Description of the token that was expected at `offset`.
+    pub fn expected(&self) -> Option<&'static str> {
+        self.inner.expected
+    }
+
+    /// Description of the token that was found at `offset` instead.
+    pub fn found(&self) -> Option<&str> {
+        self.inner.found.as_deref()
+    }
+
+    /// Error about the character `found` at `offset`, `None` for the end of input.
+    pub(crate) fn syntax(
+        kind: ErrorKind,
+        offset: usize,
+        expected: &'static str,
+        found: Option<u8>,
+    ) -> Self {
+        let mut error = Error::from(kind);
+        error.inner.offset = Some(offset);
+        error.inner.expected = Some(expected);
+        error.inner.found = Some(match found {
+            Some(ch) if ch.is_ascii_graphic() || ch == b' ' => format!("`{}`", ch as char),
+            Some(ch) => format!("byte 0x{:02x}", ch),
+            None => "end of input".to_owned(),
+        });
+        error
+    }
+
+    /// Sets the offset of an error that doesn't have one yet.
+    pub(crate) fn fix_offset(mut self, offset: usize) -> Self {
+        self.inner.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Prepends the dictionary key or list index that the error happened under.
+    pub(crate) fn prepend_path(mut self, segment: fmt::Arguments) -> Self {
+        self.inner.path = format!("{}{}", segment, self.inner.path);
+        self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            inner: Box::new(ErrorImpl {
+                kind,
+                offset: None,
+                path: String::new(),
+                expected: None,
+                found: None,
+            }),
+        }
+    }
 }
 
 impl ser::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Message(msg.to_string())
+        ErrorKind::Message(msg.to_string()).into()
     }
 }
 
 impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Message(msg.to_string())
+        ErrorKind::Message(msg.to_string()).into()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            ErrorKind::Eof.into()
+        } else {
+            ErrorKind::Io(error).into()
+        }
+    }
+}
+
+/// `Io` errors are equal if their `io::ErrorKind` is.
+impl PartialEq for ErrorKind {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ErrorKind::Message(a), ErrorKind::Message(b)) => a == b,
+            (ErrorKind::Io(a), ErrorKind::Io(b)) => a.kind() == b.kind(),
+            (ErrorKind::IntegerOverflow(a), ErrorKind::IntegerOverflow(b)) => a == b,
+            (ErrorKind::NotBencode(a), ErrorKind::NotBencode(b)) => a == b,
+            (ErrorKind::RecursionLimitExceeded(a), ErrorKind::RecursionLimitExceeded(b)) => a == b,
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            ErrorKind::Message(msg) => msg,
+            ErrorKind::Io(error) => return Display::fmt(error, formatter),
+            ErrorKind::Eof => "unexpected end of input",
+            ErrorKind::Syntax => "invalid syntax",
+            ErrorKind::ExpectedNull => "expected null",
+            ErrorKind::ExpectedBoolean => "expected boolean",
+            ErrorKind::ExpectedInteger => "expected integer",
+            ErrorKind::IntegerOverflow(name) => {
+                return write!(formatter, "integer out of range for {}", name);
+            }
+            ErrorKind::ExpectedFloat => "expected float",
+            ErrorKind::NonFiniteFloat => "float is NaN or infinite",
+            ErrorKind::ExpectedString => "expected string",
+            ErrorKind::InvalidUtf8 => "string is not valid UTF-8",
+            ErrorKind::ExpectedList => "expected list",
+            ErrorKind::ExpectedListEnd => "expected end of list",
+            ErrorKind::ExpectedDictionary => "expected dictionary",
+            ErrorKind::ExpectedDictionaryEnd => "expected end of dictionary",
+            ErrorKind::KeyMustBeAString => "dictionary key must be a string",
+            ErrorKind::DuplicateKey => "duplicate dictionary key",
+            ErrorKind::UnsortedKeys => "dictionary keys are not sorted",
+            ErrorKind::LeadingZero => "number has a leading zero",
+            ErrorKind::NegativeZero => "integer zero has a minus sign",
+            ErrorKind::TrailingZero => "fraction has a trailing zero",
+            ErrorKind::TrailingCharacters => "trailing characters after the value",
+            ErrorKind::InvalidNesting => "list or dictionary is not balanced",
+            ErrorKind::NotBencode(name) => {
+                return write!(formatter, "{} has no bencode form", name);
+            }
+            ErrorKind::RecursionLimitExceeded(depth) => {
+                return write!(formatter, "nesting is deeper than {} levels", depth);
+            }
+        };
+        formatter.write_str(description)
     }
 }
 
 impl Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str(std::error::Error::description(self))
+        Display::fmt(self.kind(), formatter)?;
+        if let (Some(expected), Some(found)) = (self.expected(), self.found()) {
+            write!(formatter, " (expected {}, found {})", expected, found)?;
+        }
+        match (self.path(), self.offset()) {
+            ("", Some(offset)) => write!(formatter, " at offset {}", offset),
+            (path, Some(offset)) => write!(formatter, " at {}, offset {}", path, offset),
+            _ => Ok(()),
+        }
     }
 }
 
 impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Message(ref msg) => msg,
-            Error::Eof => "unexpected end of input",
-            //TODO: cover all variants
-            _ => "uncovered case",
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.kind() {
+            ErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_display() {
+    use std::error::Error as _;
+
+    struct FailingWriter;
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
     }
+
+    let error = crate::from_str::<Vec<u8>>("li1ei-2ee").unwrap_err();
+    assert_eq!(
+        "expected integer (expected digit, found `-`) at [1], offset 5",
+        error.to_string()
+    );
+    assert!(error.source().is_none());
+    assert_eq!(
+        "trailing characters after the value (expected end of input, found `x`) at offset 3",
+        crate::from_str::<u8>("i1ex").unwrap_err().to_string()
+    );
+
+    let error = crate::to_writer(FailingWriter, &1).unwrap_err();
+    assert_eq!("pipe closed", error.to_string());
+    let source = error.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::BrokenPipe, source.kind());
 }
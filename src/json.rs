@@ -0,0 +1,623 @@
+//! Lossless transcoding between JSON and 1code, behind the `json` feature.
+//!
+//! Both ways are streamed: 1code is passed from `deserialize_any` straight into
+//! the JSON serializer, and JSON is read token by token into an `Emitter`, so no
+//! document is held in memory.
+//! |          1coded           |            JSON             |
+//! | ------------------------- | --------------------------- |
+//! | N, T, F                   | null, true, false           |
+//! | i-1e, i1.50e, i1…e        | -1, 1.50, 1…                |
+//! | 1:a                       | "a"                         |
+//! | 1:\xff                    | error, or "/w==" for base64 |
+//! | l…e, d…e                  | […], {…}                    |
+//!
+//! Numbers keep their exact digits both ways, JSON exponents are expanded. Only
+//! the integer `-0` of JSON loses its sign, because 1code reads `i-0e` as a float.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer as _, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::de::{Deserializer, DEFAULT_MAX_DEPTH};
+use crate::error::{Error, ErrorKind, Result};
+use crate::read;
+use crate::ser::Emitter;
+
+mod reader;
+
+use self::reader::JsonReader;
+
+/// Longest number that a JSON exponent is expanded to.
+const MAX_EXPANDED_DIGITS: usize = 4096;
+
+/// How byte strings that aren't UTF-8 are written to JSON.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteStrings {
+    /// Fail at the first one, with its offset and path.
+    #[default]
+    Error,
+    /// Write them as base64 strings with padding, which don't read back as bytes.
+    Base64,
+}
+
+/// Options of the transcoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transcoder {
+    byte_strings: ByteStrings,
+    pretty: bool,
+    max_depth: usize,
+}
+
+impl Default for Transcoder {
+    fn default() -> Self {
+        Transcoder {
+            byte_strings: ByteStrings::default(),
+            pretty: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+impl Transcoder {
+    pub fn new() -> Self {
+        Transcoder::default()
+    }
+
+    pub fn byte_strings(mut self, byte_strings: ByteStrings) -> Self {
+        self.byte_strings = byte_strings;
+        self
+    }
+
+    /// Indent the JSON output.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Deepest nesting of JSON arrays and objects that `from_json` reads, as
+    /// `Deserializer::max_depth` does for 1code. 128 by default.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Transcodes the next value of `de` into JSON, the input may continue after it.
+    pub fn to_json<'de, R, W>(&self, de: &mut Deserializer<R>, writer: W) -> Result<()>
+    where
+        R: read::Read<'de>,
+        W: io::Write,
+    {
+        let byte_strings = self.byte_strings;
+        let exact_numbers = std::mem::replace(&mut de.exact_numbers, true);
+        let result = if self.pretty {
+            let mut ser = serde_json::Serializer::pretty(writer);
+            de.deserialize_any(TranscodeVisitor {
+                ser: &mut ser,
+                byte_strings,
+            })
+        } else {
+            let mut ser = serde_json::Serializer::new(writer);
+            de.deserialize_any(TranscodeVisitor {
+                ser: &mut ser,
+                byte_strings,
+            })
+        };
+        de.exact_numbers = exact_numbers;
+        result?.map_err(json_error)
+    }
+
+    /// Transcodes a whole JSON document into 1code, wrap unbuffered readers into
+    /// an `io::BufReader`. Errors have the byte offset in the JSON text.
+    pub fn from_json<R, W>(&self, reader: R, writer: W) -> Result<()>
+    where
+        R: io::Read,
+        W: io::Write,
+    {
+        let mut emitter = Emitter::new(writer);
+        JsonReader::new(reader, self.max_depth).transcode(&mut emitter)?;
+        emitter.finish()?;
+        Ok(())
+    }
+}
+
+/// Transcodes a whole 1code document into JSON with the default options, wrap
+/// unbuffered readers into an `io::BufReader`.
+pub fn to_json<R, W>(reader: R, writer: W) -> Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let mut de = Deserializer::from_reader(reader);
+    Transcoder::default().to_json(&mut de, writer)?;
+    de.end()
+}
+
+/// Transcodes a whole JSON document into 1code with the default options.
+pub fn from_json<R, W>(reader: R, writer: W) -> Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    Transcoder::default().from_json(reader, writer)
+}
+
+/// JSON errors become messages, which include the line and column.
+fn json_error(error: serde_json::Error) -> Error {
+    if error.is_io() {
+        return io::Error::from(error).into();
+    }
+    ErrorKind::Message(format!("JSON: {}", error)).into()
+}
+
+/// Serializes the value that `de` yields, transcoding it on the fly. A failure
+/// of `de` is kept aside, so it reaches the caller with its own type.
+struct Transcode<'de, D: de::Deserializer<'de>> {
+    de: RefCell<Option<D>>,
+    error: RefCell<Option<D::Error>>,
+    byte_strings: ByteStrings,
+    marker: PhantomData<&'de ()>,
+}
+
+impl<'de, D: de::Deserializer<'de>> Serialize for Transcode<'de, D> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let de = match self.de.borrow_mut().take() {
+            Some(de) => de,
+            None => return Err(ser::Error::custom("value is transcoded twice")),
+        };
+        match de.deserialize_any(TranscodeVisitor {
+            ser: serializer,
+            byte_strings: self.byte_strings,
+        }) {
+            Ok(result) => result,
+            Err(error) => {
+                let ser_error = ser::Error::custom(&error);
+                *self.error.borrow_mut() = Some(error);
+                Err(ser_error)
+            }
+        }
+    }
+}
+
+/// Runs `serialize` on a `Transcode` of `de` and hands back a failure of `de`
+/// as is, while failures of the serializer are returned inside `Ok`.
+fn transcode_with<'de, D, T, E>(
+    de: D,
+    byte_strings: ByteStrings,
+    serialize: impl FnOnce(&Transcode<'de, D>) -> std::result::Result<T, E>,
+) -> std::result::Result<std::result::Result<T, E>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let transcode = Transcode {
+        de: RefCell::new(Some(de)),
+        error: RefCell::new(None),
+        byte_strings,
+        marker: PhantomData,
+    };
+    let result = serialize(&transcode);
+    match transcode.error.into_inner() {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+/// Returns a serializer failure from a visitor method.
+macro_rules! try_ser {
+    ($result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(error) => return Ok(Err(error)),
+        }
+    };
+}
+
+struct TranscodeVisitor<S> {
+    ser: S,
+    byte_strings: ByteStrings,
+}
+
+impl<'de, S: ser::Serializer> Visitor<'de> for TranscodeVisitor<S> {
+    type Value = std::result::Result<S::Ok, S::Error>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_bool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_i64(value))
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_i128(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_u64(value))
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_u128(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_f64(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_str(value))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> std::result::Result<Self::Value, E> {
+        match self.byte_strings {
+            ByteStrings::Base64 => Ok(self.ser.serialize_str(&base64(value))),
+            ByteStrings::Error => Err(de::Error::custom(ErrorKind::InvalidUtf8)),
+        }
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_unit())
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+        Ok(self.ser.serialize_none())
+    }
+
+    fn visit_some<D>(self, de: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de.deserialize_any(self)
+    }
+
+    /// Exact numbers arrive here, see `Deserializer::exact_numbers`. Documents
+    /// have no newtype structs, so they can't pass anything else.
+    fn visit_newtype_struct<D>(self, de: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let digits = String::deserialize(de)?;
+        serialize_json_number(self.ser, &digits)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = try_ser!(self.ser.serialize_seq(seq.size_hint()));
+        while let Some(result) = seq.next_element_seed(ElementSeed {
+            list: &mut list,
+            byte_strings: self.byte_strings,
+        })? {
+            try_ser!(result);
+        }
+        Ok(list.end())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = try_ser!(self.ser.serialize_map(map.size_hint()));
+        while let Some(result) = map.next_key_seed(KeySeed {
+            dict: &mut dict,
+            byte_strings: self.byte_strings,
+        })? {
+            try_ser!(result);
+            try_ser!(map.next_value_seed(ValueSeed {
+                dict: &mut dict,
+                byte_strings: self.byte_strings,
+            })?);
+        }
+        Ok(dict.end())
+    }
+}
+
+struct ElementSeed<'a, S> {
+    list: &'a mut S,
+    byte_strings: ByteStrings,
+}
+
+impl<'de, S: SerializeSeq> DeserializeSeed<'de> for ElementSeed<'_, S> {
+    type Value = std::result::Result<(), S::Error>;
+
+    fn deserialize<D>(self, de: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let list = self.list;
+        transcode_with(de, self.byte_strings, |value| list.serialize_element(value))
+    }
+}
+
+struct KeySeed<'a, S> {
+    dict: &'a mut S,
+    byte_strings: ByteStrings,
+}
+
+impl<'de, S: SerializeMap> DeserializeSeed<'de> for KeySeed<'_, S> {
+    type Value = std::result::Result<(), S::Error>;
+
+    fn deserialize<D>(self, de: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let dict = self.dict;
+        transcode_with(de, self.byte_strings, |key| dict.serialize_key(key))
+    }
+}
+
+struct ValueSeed<'a, S> {
+    dict: &'a mut S,
+    byte_strings: ByteStrings,
+}
+
+impl<'de, S: SerializeMap> DeserializeSeed<'de> for ValueSeed<'_, S> {
+    type Value = std::result::Result<(), S::Error>;
+
+    fn deserialize<D>(self, de: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let dict = self.dict;
+        transcode_with(de, self.byte_strings, |value| dict.serialize_value(value))
+    }
+}
+
+/// Writes the exact `digits` of a 1code number into JSON.
+fn serialize_json_number<S, E>(
+    ser: S,
+    digits: &str,
+) -> std::result::Result<std::result::Result<S::Ok, S::Error>, E>
+where
+    S: ser::Serializer,
+    E: de::Error,
+{
+    let number = json_number(digits);
+    if !is_json_number(&number) {
+        return Err(de::Error::custom(format_args!(
+            "number {} has no JSON form",
+            digits
+        )));
+    }
+    let raw = RawValue::from_string(number).map_err(de::Error::custom)?;
+    Ok(raw.serialize(ser))
+}
+
+/// 1code decimal digits without the leading zeros that JSON doesn't allow.
+fn json_number(digits: &str) -> String {
+    let (sign, unsigned) = match digits.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", digits),
+    };
+    let trimmed = unsigned.trim_start_matches('0');
+    if trimmed.is_empty() || trimmed.starts_with('.') {
+        format!("{}0{}", sign, trimmed)
+    } else {
+        format!("{}{}", sign, trimmed)
+    }
+}
+
+/// Whether `number` follows the number grammar of JSON,
+/// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`.
+fn is_json_number(number: &str) -> bool {
+    let bytes = number.as_bytes();
+    let mut index = usize::from(bytes.first() == Some(&b'-'));
+    let digits = |index: &mut usize| {
+        let start = *index;
+        while bytes.get(*index).is_some_and(u8::is_ascii_digit) {
+            *index += 1;
+        }
+        *index - start
+    };
+    let integer = index;
+    match digits(&mut index) {
+        0 => return false,
+        1 => {}
+        _ if bytes[integer] == b'0' => return false,
+        _ => {}
+    }
+    if bytes.get(index) == Some(&b'.') {
+        index += 1;
+        if digits(&mut index) == 0 {
+            return false;
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(index) {
+        index += 1;
+        if let Some(b'+' | b'-') = bytes.get(index) {
+            index += 1;
+        }
+        if digits(&mut index) == 0 {
+            return false;
+        }
+    }
+    index == bytes.len()
+}
+
+/// Plain decimal digits of a JSON number, `None` if they'd be too many.
+/// |  JSON   | plain |
+/// | ------- | ----- |
+/// | 1.50    | 1.50  |
+/// | 1.5e3   | 1500  |
+/// | -25E-3  | -0.025 |
+fn expand_exponent(number: &str) -> Option<String> {
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(index) => (&number[..index], number[index + 1..].parse::<i64>().ok()?),
+        None => return Some(number.to_owned()),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let (integer, fraction) = match mantissa.find('.') {
+        Some(index) => (&mantissa[..index], &mantissa[index + 1..]),
+        None => (mantissa, ""),
+    };
+    let digits = format!("{}{}", integer, fraction);
+    // Position of the decimal point in `digits` after the shift.
+    let point = integer.len() as i64 + exponent;
+    if point.unsigned_abs() as usize > MAX_EXPANDED_DIGITS {
+        return None;
+    }
+    let plain = if point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else if point as usize >= digits.len() {
+        format!("{}{}", digits, "0".repeat(point as usize - digits.len()))
+    } else {
+        format!(
+            "{}.{}",
+            &digits[..point as usize],
+            &digits[point as usize..]
+        )
+    };
+    Some(format!("{}{}", sign, plain))
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0_u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(group >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[test]
+fn test_json() {
+    fn to_json_str(input: impl AsRef<[u8]>, transcoder: Transcoder) -> Result<String> {
+        let mut output = Vec::new();
+        let mut de = Deserializer::from_slice(input.as_ref());
+        transcoder.to_json(&mut de, &mut output)?;
+        de.end()?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn from_json_str(input: &str) -> Result<String> {
+        let mut output = Vec::new();
+        from_json(input.as_bytes(), &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    let onecode = "d1:aN1:blTFi-1ei18446744073709551616ei-0e\
+                   i1.50ei007ei-00.5ei123456789012345678901234567890123456789012ee1:c0:e";
+    let json = r#"{"a":null,"b":[true,false,-1,18446744073709551616,-0,1.50,7,-0.5,123456789012345678901234567890123456789012],"c":""}"#;
+    assert_eq!(to_json_str(onecode, Transcoder::new()).unwrap(), json);
+    let mut output = Vec::new();
+    to_json(onecode.as_bytes(), &mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), json);
+    assert_eq!(
+        from_json_str(json).unwrap(),
+        "d1:aN1:blTFi-1ei18446744073709551616ei0ei1.50ei7ei-0.5ei123456789012345678901234567890123456789012ee1:c0:e"
+    );
+    assert_eq!(
+        from_json_str(r#"[1e3, -2.5E-2, 0.1e1, 12e-1, "é\n"]"#).unwrap(),
+        "li1000ei-0.025ei1ei1.2e3:\u{e9}\ne"
+    );
+    let error = to_json_str(b"d1:al3:abc1:\xffee", Transcoder::new()).unwrap_err();
+    assert_eq!(error.path(), ".a[1]");
+    assert_eq!(error.offset(), Some(10));
+    assert!(error.to_string().contains("not valid UTF-8"));
+    let base64 = Transcoder::new().byte_strings(ByteStrings::Base64);
+    assert_eq!(
+        to_json_str(b"l1:\xff2:\xff\x003:\xff\x00\x010:e", base64).unwrap(),
+        r#"["/w==","/wA=","/wAB",""]"#
+    );
+    assert_eq!(
+        to_json_str(b"d2:\xff\xffi1ee", base64).unwrap(),
+        r#"{"//8=":1}"#
+    );
+    assert_eq!(
+        to_json_str("di1eTe", Transcoder::new()).unwrap(),
+        r#"{"1":true}"#
+    );
+    assert_eq!(
+        to_json_str("d1:ali1ee1:bdee", Transcoder::new().pretty(true)).unwrap(),
+        "{\n  \"a\": [\n    1\n  ],\n  \"b\": {}\n}"
+    );
+    assert_eq!(
+        &ErrorKind::Eof,
+        to_json_str("li1e", Transcoder::new()).unwrap_err().kind()
+    );
+    assert_eq!(from_json_str("[1,").unwrap_err().kind(), &ErrorKind::Eof);
+    assert!(from_json_str("1e99999").is_err());
+    assert_eq!(
+        from_json_str(
+            r#" { "a" : [ ] , "b" : { } , "c" : "\u00e9\ud83d\ude00\"\\\/\b\f\n\r\t" } "#
+        )
+        .unwrap(),
+        "d1:ale1:bde1:c14:\u{e9}\u{1f600}\"\\/\x08\x0c\n\r\te"
+    );
+    for (json, offset) in [
+        ("[1,]", 3),
+        (r#"{"a":1,}"#, 7),
+        ("[1 2]", 3),
+        ("01", 0),
+        ("tru", 3),
+        (r#""\u0041\ud800""#, 7),
+        ("\"a\nb\"", 2),
+        ("1 2", 2),
+    ] {
+        let error = from_json_str(json).unwrap_err();
+        assert_eq!(error.offset(), Some(offset), "{}", json);
+    }
+
+    let deep = "[".repeat(20_000);
+    let error = from_json_str(&deep).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::RecursionLimitExceeded(128));
+    assert_eq!(error.offset(), Some(128));
+    let nested = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    assert!(from_json_str(&nested).is_ok());
+    let nested = format!("{}1{}", "[{\"a\":".repeat(50_000), "}]".repeat(50_000));
+    let mut output = Vec::new();
+    Transcoder::new()
+        .max_depth(usize::MAX)
+        .from_json(nested.as_bytes(), &mut output)
+        .unwrap();
+    assert_eq!(output.len(), 50_000 * 7 + 3);
+
+    assert_eq!(
+        to_json_str(
+            r#"d28:$serde_json::private::Number10:{"evil":1}e"#,
+            Transcoder::new()
+        )
+        .unwrap(),
+        r#"{"$serde_json::private::Number":"{\"evil\":1}"}"#
+    );
+    assert_eq!(
+        to_json_str("d25:$onecode::private::BigInt2:[]e", Transcoder::new()).unwrap(),
+        r#"{"$onecode::private::BigInt":"[]"}"#
+    );
+    assert_eq!(
+        from_json_str(r#"{"$serde_json::private::Number":"1"}"#).unwrap(),
+        "d28:$serde_json::private::Number1:1e"
+    );
+    for number in ["0", "-0.5", "1.5e3", "2E-07", "10e+1"] {
+        assert!(is_json_number(number), "{}", number);
+    }
+    for number in ["", "-", "01", "1.", ".5", "1e", "1e+", "+1", "{}", "1 "] {
+        assert!(!is_json_number(number), "{}", number);
+    }
+}
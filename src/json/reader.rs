@@ -0,0 +1,297 @@
+//! Pull reader of JSON text that writes every value into an `Emitter` as soon as
+//! it is read, without recursion and without holding the document.
+
+use std::io;
+use std::str::FromStr;
+
+use super::{expand_exponent, is_json_number};
+use crate::error::{Error, ErrorKind, Result};
+use crate::number::{BigInt, Decimal};
+use crate::read::{IoRead, Read as _};
+use crate::ser::Emitter;
+
+/// A JSON array or object that isn't closed yet.
+#[derive(Clone, Copy, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+pub(super) struct JsonReader<R> {
+    read: IoRead<R>,
+    stack: Vec<Container>,
+    max_depth: usize,
+    scratch: Vec<u8>,
+}
+
+impl<R> JsonReader<R>
+where
+    R: io::Read,
+{
+    pub(super) fn new(reader: R, max_depth: usize) -> Self {
+        JsonReader {
+            read: IoRead::new(reader),
+            stack: Vec::new(),
+            max_depth,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Transcodes one JSON value and checks that only whitespace follows it.
+    pub(super) fn transcode<W: io::Write>(&mut self, emitter: &mut Emitter<W>) -> Result<()> {
+        // Whether an array or object was opened and its first entry comes next.
+        let mut entry = self.value(emitter)?;
+        loop {
+            if entry {
+                if self.stack.last() == Some(&Container::Object) {
+                    self.key(emitter)?;
+                }
+                entry = self.value(emitter)?;
+                continue;
+            }
+            let container = match self.stack.last() {
+                Some(&container) => container,
+                None => break,
+            };
+            self.skip_whitespace()?;
+            match (self.peek()?, container) {
+                (Some(b','), _) => {
+                    self.read.next()?;
+                    entry = true;
+                }
+                (Some(b']'), Container::Array) | (Some(b'}'), Container::Object) => {
+                    self.read.next()?;
+                    self.stack.pop();
+                    emitter.end()?;
+                }
+                (_, Container::Array) => return Err(self.peek_error("`,` or `]`")),
+                (_, Container::Object) => return Err(self.peek_error("`,` or `}`")),
+            }
+        }
+        self.skip_whitespace()?;
+        match self.peek()? {
+            Some(_) => Err(self.peek_error_kind(ErrorKind::TrailingCharacters, "end of input")),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes a scalar, or opens an array or object and tells whether an entry
+    /// follows. Empty ones are closed right away.
+    fn value<W: io::Write>(&mut self, emitter: &mut Emitter<W>) -> Result<bool> {
+        self.skip_whitespace()?;
+        let (container, close) = match self.peek()? {
+            Some(b'[') => (Container::Array, b']'),
+            Some(b'{') => (Container::Object, b'}'),
+            Some(b'"') => {
+                let offset = self.read.offset();
+                let string = self.string()?;
+                emitter
+                    .str(string)
+                    .map_err(|error| error.fix_offset(offset))?;
+                return Ok(false);
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                self.number(emitter)?;
+                return Ok(false);
+            }
+            Some(b't') => {
+                self.literal(b"true")?;
+                emitter.bool(true)?;
+                return Ok(false);
+            }
+            Some(b'f') => {
+                self.literal(b"false")?;
+                emitter.bool(false)?;
+                return Ok(false);
+            }
+            Some(b'n') => {
+                self.literal(b"null")?;
+                emitter.null()?;
+                return Ok(false);
+            }
+            Some(_) => return Err(self.peek_error("value")),
+            None => return Err(self.peek_error_kind(ErrorKind::Eof, "value")),
+        };
+        if self.stack.len() >= self.max_depth {
+            return Err(
+                Error::from(ErrorKind::RecursionLimitExceeded(self.max_depth))
+                    .fix_offset(self.read.offset()),
+            );
+        }
+        self.read.next()?;
+        match container {
+            Container::Array => emitter.begin_list()?,
+            Container::Object => emitter.begin_dict()?,
+        }
+        self.skip_whitespace()?;
+        if self.peek()? == Some(close) {
+            self.read.next()?;
+            emitter.end()?;
+            return Ok(false);
+        }
+        self.stack.push(container);
+        Ok(true)
+    }
+
+    /// Writes an object key and consumes the `:` after it.
+    fn key<W: io::Write>(&mut self, emitter: &mut Emitter<W>) -> Result<()> {
+        self.skip_whitespace()?;
+        if self.peek()? != Some(b'"') {
+            return Err(self.peek_error("string key"));
+        }
+        let key = self.string()?;
+        emitter.key(key)?;
+        self.skip_whitespace()?;
+        if self.peek()? != Some(b':') {
+            return Err(self.peek_error("`:`"));
+        }
+        self.read.next()?;
+        Ok(())
+    }
+
+    /// Number with its digits as written, exponents expanded.
+    fn number<W: io::Write>(&mut self, emitter: &mut Emitter<W>) -> Result<()> {
+        let offset = self.read.offset();
+        self.scratch.clear();
+        while let Some(ch @ (b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) = self.peek()? {
+            self.scratch.push(ch);
+            self.read.next()?;
+        }
+        // Only ASCII was collected.
+        let number = std::str::from_utf8(&self.scratch).unwrap_or_default();
+        let syntax = || Error::syntax(ErrorKind::Syntax, offset, "JSON number", Some(b'-'));
+        if !is_json_number(number) {
+            return Err(syntax());
+        }
+        let plain = expand_exponent(number).ok_or_else(|| {
+            Error::from(ErrorKind::Message(format!(
+                "number {} has too large an exponent",
+                number
+            )))
+            .fix_offset(offset)
+        })?;
+        if plain.contains('.') {
+            emitter.decimal(&Decimal::from_str(&plain).map_err(|_| syntax())?)
+        } else {
+            emitter.big_int(&BigInt::from_str(&plain).map_err(|_| syntax())?)
+        }
+    }
+
+    /// Reads a string with its escapes resolved.
+    fn string(&mut self) -> Result<&str> {
+        let offset = self.read.offset();
+        self.read.next()?;
+        self.scratch.clear();
+        loop {
+            match self.read.next()? {
+                Some(b'"') => break,
+                Some(b'\\') => self.escape()?,
+                Some(ch) if ch < 0x20 => {
+                    return Err(Error::syntax(
+                        ErrorKind::Syntax,
+                        self.read.offset() - 1,
+                        "escaped control character",
+                        Some(ch),
+                    ))
+                }
+                Some(ch) => self.scratch.push(ch),
+                None => return Err(self.peek_error_kind(ErrorKind::Eof, "`\"`")),
+            }
+        }
+        std::str::from_utf8(&self.scratch)
+            .map_err(|_| Error::from(ErrorKind::InvalidUtf8).fix_offset(offset))
+    }
+
+    /// Resolves the escape after a `\`, surrogate pairs included.
+    fn escape(&mut self) -> Result<()> {
+        let ch = match self.read.next()? {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0c',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                let offset = self.read.offset() - 2;
+                let surrogate =
+                    || Error::syntax(ErrorKind::Syntax, offset, "surrogate pair", Some(b'\\'));
+                let high = self.hex()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    if self.read.next()? != Some(b'\\') || self.read.next()? != Some(b'u') {
+                        return Err(surrogate());
+                    }
+                    let low = self.hex()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(surrogate());
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(surrogate)?
+            }
+            found => {
+                let offset = self.read.offset().saturating_sub(1);
+                return Err(Error::syntax(ErrorKind::Syntax, offset, "escape", found));
+            }
+        };
+        let mut buffer = [0; 4];
+        self.scratch
+            .extend_from_slice(ch.encode_utf8(&mut buffer).as_bytes());
+        Ok(())
+    }
+
+    /// Four hex digits of a `\u` escape.
+    fn hex(&mut self) -> Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = match self.peek()? {
+                Some(ch) => (ch as char).to_digit(16),
+                None => None,
+            };
+            match digit {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(self.peek_error("hex digit")),
+            }
+            self.read.next()?;
+        }
+        Ok(code)
+    }
+
+    fn literal(&mut self, literal: &'static [u8]) -> Result<()> {
+        for &expected in literal {
+            if self.peek()? != Some(expected) {
+                return Err(self.peek_error("`true`, `false` or `null`"));
+            }
+            self.read.next()?;
+        }
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek()? {
+            self.read.next()?;
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        self.read.peek()
+    }
+
+    fn peek_error(&mut self, expected: &'static str) -> Error {
+        self.peek_error_kind(ErrorKind::Syntax, expected)
+    }
+
+    /// Error about the next byte, which isn't consumed yet.
+    fn peek_error_kind(&mut self, kind: ErrorKind, expected: &'static str) -> Error {
+        let found = self.peek().unwrap_or(None);
+        let kind = match (kind, found) {
+            (ErrorKind::Syntax, None) => ErrorKind::Eof,
+            (kind, _) => kind,
+        };
+        Error::syntax(kind, self.read.offset(), expected, found)
+    }
+}
@@ -1,7 +1,22 @@
-mod de;
+#[macro_use]
+mod macros;
+
+pub mod de;
+mod dialect;
 mod error;
+#[cfg(feature = "json")]
+pub mod json;
+mod number;
+pub mod read;
 pub mod ser;
+mod value;
 
-//pub use de::{from_str, Deserializer};
-pub use error::{Error, Result};
-pub use ser::{to_string, Serializer};
+pub use de::{from_reader, from_slice, from_str, Deserializer};
+pub use dialect::{Dialect, Literals};
+pub use error::{Error, ErrorKind, Result};
+pub use number::{BigInt, Decimal};
+pub use ser::{
+    reformat, to_string, to_string_pretty, to_vec, to_vec_canonical, to_writer, Serializer,
+    UnitVariant,
+};
+pub use value::{from_value, to_value, Value};
@@ -0,0 +1,214 @@
+/// Builds a `Value` from 1code-shaped literals, in the manner of `serde_json::json!`.
+/// Any expression that implements `Serialize` can be used in a value position,
+/// and dictionary keys can be any expression that converts into `Vec<u8>`,
+/// such as `&str` and `String`.
+/// |           onecode!           |       1coded        |
+/// | ---------------------------- | ------------------- |
+/// | null                         | N                   |
+/// | [true, 1, "x"]               | lTi1e1:xe           |
+/// | { "b": 1, "a": [1.5, null] } | d1:ali1.5eNe1:bi1ee |
+///
+/// Dictionaries keep their keys sorted, so the encoding matches `ser::to_string`
+/// of a `BTreeMap` rather than of a struct with unsorted fields.
+#[macro_export]
+macro_rules! onecode {
+    ($($onecode:tt)+) => {
+        $crate::onecode_internal!($($onecode)+)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! onecode_internal {
+    // Done with the list elements, with or without a trailing comma.
+    (@list [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+    (@list [$($elems:expr),*]) => {
+        vec![$($elems),*]
+    };
+
+    // Next element is a literal or a nested list or dictionary.
+    (@list [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!(null)] $($rest)*)
+    };
+    (@list [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!(true)] $($rest)*)
+    };
+    (@list [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!(false)] $($rest)*)
+    };
+    (@list [$($elems:expr,)*] [$($list:tt)*] $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!([$($list)*])] $($rest)*)
+    };
+    (@list [$($elems:expr,)*] {$($dict:tt)*} $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!({$($dict)*})] $($rest)*)
+    };
+
+    // Next element is an expression, followed by a comma or last.
+    (@list [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!($next),] $($rest)*)
+    };
+    (@list [$($elems:expr,)*] $last:expr) => {
+        $crate::onecode_internal!(@list [$($elems,)* $crate::onecode_internal!($last)])
+    };
+
+    // Comma after the most recent element.
+    (@list [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::onecode_internal!(@list [$($elems,)*] $($rest)*)
+    };
+
+    // Unexpected token after the most recent element.
+    (@list [$($elems:expr),*] $unexpected:tt $($rest:tt)*) => {
+        $crate::onecode_unexpected!($unexpected)
+    };
+
+    // Done with the dictionary entries.
+    (@dict $dict:ident () () ()) => {};
+
+    // Insert the current entry, followed by a comma or last.
+    (@dict $dict:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $dict.insert(($($key)+).into(), $value);
+        $crate::onecode_internal!(@dict $dict () ($($rest)*) ($($rest)*));
+    };
+    (@dict $dict:ident [$($key:tt)+] ($value:expr) $unexpected:tt $($rest:tt)*) => {
+        $crate::onecode_unexpected!($unexpected);
+    };
+    (@dict $dict:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $dict.insert(($($key)+).into(), $value);
+    };
+
+    // Next value is a literal or a nested list or dictionary.
+    (@dict $dict:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!(null)) $($rest)*);
+    };
+    (@dict $dict:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!(true)) $($rest)*);
+    };
+    (@dict $dict:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!(false)) $($rest)*);
+    };
+    (@dict $dict:ident ($($key:tt)+) (: [$($list:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!([$($list)*])) $($rest)*);
+    };
+    (@dict $dict:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!({$($map)*})) $($rest)*);
+    };
+
+    // Next value is an expression, followed by a comma or last.
+    (@dict $dict:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!($value)) , $($rest)*);
+    };
+    (@dict $dict:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict [$($key)+] ($crate::onecode_internal!($value)));
+    };
+
+    // Missing value or colon for the last entry.
+    (@dict $dict:ident ($($key:tt)+) (:) $copy:tt) => {
+        $crate::onecode_internal!();
+    };
+    (@dict $dict:ident ($($key:tt)+) () $copy:tt) => {
+        $crate::onecode_internal!();
+    };
+
+    // Colon without a key, or comma inside a key.
+    (@dict $dict:ident () (: $($rest:tt)*) ($colon:tt $($copy:tt)*)) => {
+        $crate::onecode_unexpected!($colon);
+    };
+    (@dict $dict:ident ($($key:tt)*) (, $($rest:tt)*) ($comma:tt $($copy:tt)*)) => {
+        $crate::onecode_unexpected!($comma);
+    };
+
+    // Parenthesized key, or munch one more token into the key.
+    (@dict $dict:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict ($key) (: $($rest)*) (: $($rest)*));
+    };
+    (@dict $dict:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::onecode_internal!(@dict $dict ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    (null) => {
+        $crate::Value::Null
+    };
+    (true) => {
+        $crate::Value::Bool(true)
+    };
+    (false) => {
+        $crate::Value::Bool(false)
+    };
+    ([]) => {
+        $crate::Value::List(vec![])
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::Value::List($crate::onecode_internal!(@list [] $($tt)+))
+    };
+    ({}) => {
+        $crate::Value::Dict(::std::collections::BTreeMap::new())
+    };
+    ({ $($tt:tt)+ }) => {
+        $crate::Value::Dict({
+            let mut dict = ::std::collections::BTreeMap::new();
+            $crate::onecode_internal!(@dict dict () ($($tt)+) ($($tt)+));
+            dict
+        })
+    };
+
+    // Any other value is serialized, which fails only for non-string
+    // dictionary keys and failing `Serialize` implementations.
+    ($other:expr) => {
+        $crate::to_value(&$other).unwrap()
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! onecode_unexpected {
+    () => {};
+}
+
+#[test]
+fn test_onecode() {
+    use std::collections::BTreeMap;
+
+    use crate::{to_string, Value};
+
+    assert_eq!(onecode!(null), Value::Null);
+    assert_eq!(onecode!([]), Value::List(vec![]));
+    assert_eq!(onecode!({}), Value::Dict(BTreeMap::new()));
+    assert_eq!(
+        to_string(&onecode!({ "a": 1, "b": [true, null, "x"] })).unwrap(),
+        "d1:ai1e1:blTN1:xee"
+    );
+
+    #[derive(serde::Serialize)]
+    struct Inner {
+        a: Vec<u16>,
+        b: Option<()>,
+    }
+
+    let key = "c".to_owned();
+    let list = vec![1u16, 2, 3];
+    let value = onecode!({
+        "a": -1.5,
+        "b": { "a": list.clone(), "b": null },
+        key: [false, {}, [], list.len() as u8 + 1,],
+        ("d".to_owned() + "e"): "привет мир",
+    });
+    assert_eq!(value["b"]["a"][2].as_u64(), Some(3));
+    assert_eq!(value["c"][3].as_u64(), Some(4));
+
+    let mut dict = BTreeMap::new();
+    dict.insert("a", Value::from(-1.5));
+    dict.insert("b", crate::to_value(&Inner { a: list, b: None }).unwrap());
+    dict.insert(
+        "c",
+        Value::List(vec![
+            Value::from(false),
+            Value::Dict(BTreeMap::new()),
+            Value::List(vec![]),
+            Value::from(4),
+        ]),
+    );
+    dict.insert("de", Value::from("привет мир"));
+    assert_eq!(to_string(&value).unwrap(), to_string(&dict).unwrap());
+}
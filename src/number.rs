@@ -0,0 +1,658 @@
+//! Numbers that don't fit into the primitive types.
+//!
+//! They are serialized as a newtype struct with a private name that carries
+//! their digits, which `ser::Serializer` writes as a number instead of a string.
+//! `de::Deserializer` hands their digits as a string to `visit_newtype_struct`:
+//! from `deserialize_any` for integers that don't fit into 128 bits, and from
+//! `deserialize_newtype_struct` for `Decimal`. Documents have no newtype structs,
+//! so no dictionary in them can pass for a number.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Impossible, Serialize, Serializer};
+
+use crate::error::{Error, ErrorKind};
+
+/// Name of the newtype struct that carries `BigInt` digits.
+pub(crate) const BIG_INT_TOKEN: &str = "$onecode::private::BigInt";
+
+/// Name of the newtype struct that carries `Decimal` digits.
+pub(crate) const DECIMAL_TOKEN: &str = "$onecode::private::Decimal";
+
+/// Integer of any size, kept as its decimal digits so that it passes through
+/// `deserialize_any` and `Value` without loss. There is no arithmetic, convert
+/// it to a primitive or parse `as_str` with a big number library.
+/// |          rust           |                  1coded                   |
+/// | ----------------------- | ----------------------------------------- |
+/// | BigInt::from(-1)        | i-1e                                      |
+/// | BigInt::from(u128::MAX) | i340282366920938463463374607431768211455e |
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    /// `-?[0-9]+` without leading zeros and without `-0`.
+    digits: String,
+}
+
+impl BigInt {
+    /// The decimal digits, with a `-` in front of negative numbers.
+    pub fn as_str(&self) -> &str {
+        &self.digits
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.digits.starts_with('-')
+    }
+
+    fn magnitude(&self) -> &str {
+        self.digits.trim_start_matches('-')
+    }
+}
+
+/// Accepts `-?[0-9]+`, leading zeros are dropped.
+impl FromStr for BigInt {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (negative, magnitude) = match s.strip_prefix('-') {
+            Some(magnitude) => (true, magnitude),
+            None => (false, s),
+        };
+        if magnitude.is_empty() || !magnitude.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ErrorKind::ExpectedInteger.into());
+        }
+        let magnitude = magnitude.trim_start_matches('0');
+        let digits = match (negative, magnitude) {
+            (_, "") => "0".to_owned(),
+            (true, magnitude) => format!("-{}", magnitude),
+            (false, magnitude) => magnitude.to_owned(),
+        };
+        Ok(BigInt { digits })
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.digits)
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Numeric order.
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let by_magnitude = |a: &BigInt, b: &BigInt| {
+            let (a, b) = (a.magnitude(), b.magnitude());
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        };
+        match (self.is_negative(), other.is_negative()) {
+            (false, false) => by_magnitude(self, other),
+            (true, true) => by_magnitude(other, self),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+macro_rules! big_int_conversions {
+    ($($ty:ident)*) => {
+        $(
+            impl From<$ty> for BigInt {
+                fn from(value: $ty) -> Self {
+                    BigInt {
+                        digits: value.to_string(),
+                    }
+                }
+            }
+
+            /// Fails with `ErrorKind::IntegerOverflow` if the value doesn't fit.
+            impl TryFrom<&BigInt> for $ty {
+                type Error = Error;
+
+                fn try_from(value: &BigInt) -> Result<Self, Error> {
+                    value
+                        .digits
+                        .parse()
+                        .map_err(|_| ErrorKind::IntegerOverflow(stringify!($ty)).into())
+                }
+            }
+        )*
+    };
+}
+
+big_int_conversions!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+impl Serialize for BigInt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(BIG_INT_TOKEN, &self.digits)
+    }
+}
+
+impl<'de> Deserialize<'de> for BigInt {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(BigIntVisitor)
+    }
+}
+
+struct BigIntVisitor;
+
+impl<'de> Visitor<'de> for BigIntVisitor {
+    type Value = BigInt;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<BigInt, E> {
+        Ok(BigInt::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<BigInt, E> {
+        Ok(BigInt::from(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<BigInt, E> {
+        Ok(BigInt::from(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<BigInt, E> {
+        Ok(BigInt::from(value))
+    }
+
+    /// Formats without big numbers, such as JSON, carry the digits as a string.
+    fn visit_str<E>(self, value: &str) -> Result<BigInt, E>
+    where
+        E: de::Error,
+    {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<BigInt, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BigInt::deserialize(deserializer)
+    }
+}
+
+/// Decimal number kept as its exact text, so that amounts such as `i0.10e` are
+/// written back byte for byte instead of going through `f64`. There is no
+/// arithmetic, convert it to a primitive or to `rust_decimal::Decimal` with the
+/// `rust_decimal` feature.
+/// |            rust            | 1coded |
+/// | -------------------------- | ------ |
+/// | "0.10".parse::<Decimal>()  | i0.10e |
+/// | Decimal::from(-1)          | i-1e   |
+/// | Decimal::try_from(0.1_f64) | i0.1e  |
+///
+/// Decimals are compared by their text, so `1.5` and `1.50` differ as their
+/// encodings do.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    /// `-?[0-9]+(.[0-9]+)?`
+    digits: String,
+}
+
+impl Decimal {
+    /// The digits with a `-` in front of negative numbers and a `.` in front of
+    /// the fraction, as they are written.
+    pub fn as_str(&self) -> &str {
+        &self.digits
+    }
+
+    /// Number of digits after the `.`.
+    pub fn scale(&self) -> usize {
+        self.digits
+            .find('.')
+            .map_or(0, |dot| self.digits.len() - dot - 1)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.digits.starts_with('-')
+    }
+
+    /// The nearest `f64`, lossy for most fractions and for long integers.
+    pub fn to_f64(&self) -> f64 {
+        self.digits.parse().unwrap_or_default()
+    }
+
+    /// The nearest `f32`, lossy for most fractions and for long integers.
+    pub fn to_f32(&self) -> f32 {
+        self.digits.parse().unwrap_or_default()
+    }
+
+    /// The integer part, fails if the fraction has digits other than zero.
+    fn to_big_int(&self) -> Result<BigInt, Error> {
+        let mut parts = self.digits.splitn(2, '.');
+        let integer = parts.next().unwrap_or_default();
+        if parts
+            .next()
+            .is_some_and(|fraction| fraction.bytes().any(|b| b != b'0'))
+        {
+            return Err(ErrorKind::ExpectedInteger.into());
+        }
+        integer.parse()
+    }
+}
+
+/// Accepts `-?[0-9]+(.[0-9]+)?` and keeps it as is, zeros included.
+impl FromStr for Decimal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if !crate::de::is_decimal(s) {
+            return Err(ErrorKind::ExpectedFloat.into());
+        }
+        Ok(Decimal {
+            digits: s.to_owned(),
+        })
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.digits)
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(value: BigInt) -> Self {
+        Decimal {
+            digits: value.digits,
+        }
+    }
+}
+
+/// Fails with `ErrorKind::ExpectedInteger` if the fraction isn't zero.
+impl TryFrom<&Decimal> for BigInt {
+    type Error = Error;
+
+    fn try_from(value: &Decimal) -> Result<Self, Error> {
+        value.to_big_int()
+    }
+}
+
+macro_rules! decimal_from_float {
+    ($($ty:ident)*) => {
+        $(
+            /// The shortest decimal that reads back as the same float, so
+            /// `0.1f32` gives `0.1`. `ser::Serializer` writes an `f32` widened to
+            /// `f64` instead, as `i0.10000000149011612e`. Fails with
+            /// `ErrorKind::ExpectedFloat` for NaN and infinities.
+            impl TryFrom<$ty> for Decimal {
+                type Error = Error;
+
+                fn try_from(value: $ty) -> Result<Self, Error> {
+                    if !value.is_finite() {
+                        return Err(ErrorKind::ExpectedFloat.into());
+                    }
+                    Ok(Decimal {
+                        digits: value.to_string(),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+decimal_from_float!(f32 f64);
+
+macro_rules! decimal_conversions {
+    ($($ty:ident)*) => {
+        $(
+            impl From<$ty> for Decimal {
+                fn from(value: $ty) -> Self {
+                    Decimal {
+                        digits: value.to_string(),
+                    }
+                }
+            }
+
+            /// Fails with `ErrorKind::ExpectedInteger` if the fraction isn't zero
+            /// and with `ErrorKind::IntegerOverflow` if the value doesn't fit.
+            impl TryFrom<&Decimal> for $ty {
+                type Error = Error;
+
+                fn try_from(value: &Decimal) -> Result<Self, Error> {
+                    $ty::try_from(&value.to_big_int()?)
+                }
+            }
+        )*
+    };
+}
+
+decimal_conversions!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Decimal {
+            digits: value.to_string(),
+        }
+    }
+}
+
+/// Fails with `ErrorKind::Message` if the value doesn't fit into 96 bits
+/// with its scale.
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<&Decimal> for rust_decimal::Decimal {
+    type Error = Error;
+
+    fn try_from(value: &Decimal) -> Result<Self, Error> {
+        rust_decimal::Decimal::from_str_exact(&value.digits)
+            .map_err(|error| ErrorKind::Message(error.to_string()).into())
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(DECIMAL_TOKEN, &self.digits)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(DECIMAL_TOKEN, DecimalVisitor)
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal number")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        Decimal::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Float(value), &self))
+    }
+
+    /// Formats without decimals, such as JSON, can carry the digits as a string.
+    fn visit_str<E>(self, value: &str) -> Result<Decimal, E>
+    where
+        E: de::Error,
+    {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+/// Takes the digits out of the payload of a `BigInt` or `Decimal` newtype struct,
+/// which is a string, without building anything in between.
+pub(crate) fn payload_digits<T>(payload: &T) -> crate::Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    payload.serialize(DigitsSerializer)
+}
+
+struct DigitsSerializer;
+
+/// Every other payload than a string is rejected.
+macro_rules! reject {
+    ($($method:ident($($ty:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> crate::Result<String> {
+                Err(ErrorKind::Syntax.into())
+            }
+        )*
+    };
+}
+
+impl Serializer for DigitsSerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_str(self, value: &str) -> crate::Result<String> {
+        Ok(value.to_owned())
+    }
+
+    reject! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> crate::Result<String> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> crate::Result<String> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> crate::Result<String> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> crate::Result<Self::SerializeSeq> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_tuple(self, _: usize) -> crate::Result<Self::SerializeTuple> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> crate::Result<Self::SerializeTupleStruct> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> crate::Result<Self::SerializeTupleVariant> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> crate::Result<Self::SerializeMap> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> crate::Result<Self::SerializeStruct> {
+        Err(ErrorKind::Syntax.into())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> crate::Result<Self::SerializeStructVariant> {
+        Err(ErrorKind::Syntax.into())
+    }
+}
+
+#[test]
+fn test_big_int() {
+    let digits = "-340282366920938463463374607431768211456000";
+    let big: BigInt = digits.parse().unwrap();
+    assert_eq!(big.as_str(), digits);
+    assert!(big.is_negative());
+    assert_eq!(crate::to_string(&big).unwrap(), format!("i{}e", digits));
+    assert_eq!(
+        crate::from_str::<BigInt>(&format!("i{}e", digits)).unwrap(),
+        big
+    );
+    assert_eq!(
+        &ErrorKind::IntegerOverflow("i128"),
+        i128::try_from(&big).unwrap_err().kind()
+    );
+
+    assert_eq!("-0".parse::<BigInt>().unwrap(), BigInt::from(0));
+    assert_eq!("007".parse::<BigInt>().unwrap().as_str(), "7");
+    assert!("1.5".parse::<BigInt>().is_err());
+    assert!("-".parse::<BigInt>().is_err());
+    assert_eq!(u128::try_from(&BigInt::from(u128::MAX)).unwrap(), u128::MAX);
+    assert_eq!(crate::from_str::<BigInt>("i-5e").unwrap(), BigInt::from(-5));
+    assert!(crate::from_str::<BigInt>("d25:$onecode::private::BigInt3:123e").is_err());
+    assert!(crate::from_str::<Decimal>("d26:$onecode::private::Decimal3:1.5e").is_err());
+
+    let mut sorted = vec![
+        BigInt::from(10),
+        big.clone(),
+        BigInt::from(-2),
+        BigInt::from(9),
+        BigInt::from(-10),
+    ];
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        vec![
+            big,
+            BigInt::from(-10),
+            BigInt::from(-2),
+            BigInt::from(9),
+            BigInt::from(10)
+        ]
+    );
+}
+
+#[test]
+fn test_decimal() {
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Payment {
+        amount: Decimal,
+        fee: Decimal,
+    }
+
+    let test_1code = "d6:amounti1234567890123456789.10e3:feei-0.000ee";
+    let payment: Payment = crate::from_str(test_1code).unwrap();
+    assert_eq!(payment.amount.as_str(), "1234567890123456789.10");
+    assert_eq!(payment.amount.scale(), 2);
+    assert_eq!(payment.fee.scale(), 3);
+    assert!(payment.fee.is_negative());
+    assert_eq!(crate::to_string(&payment).unwrap(), test_1code);
+    assert_eq!(payload_digits("-0.000").unwrap(), "-0.000");
+    assert_eq!(
+        &ErrorKind::Syntax,
+        payload_digits(&[1, 2]).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::ExpectedFloat,
+        crate::from_str::<Decimal>("i1.e").unwrap_err().kind()
+    );
+
+    let decimal: Decimal = "-12.000".parse().unwrap();
+    assert_eq!(i8::try_from(&decimal).unwrap(), -12);
+    assert_eq!(decimal.to_f64(), -12.0);
+    assert_eq!(
+        &ErrorKind::IntegerOverflow("u8"),
+        u8::try_from(&decimal).unwrap_err().kind()
+    );
+    assert_eq!(
+        &ErrorKind::ExpectedInteger,
+        i64::try_from(&"0.5".parse::<Decimal>().unwrap())
+            .unwrap_err()
+            .kind()
+    );
+    assert_eq!(Decimal::try_from(0.1_f64).unwrap().as_str(), "0.1");
+    assert_eq!(Decimal::try_from(0.1_f32).unwrap().as_str(), "0.1");
+    assert_eq!(crate::to_string(&0.1_f32).unwrap(), "i0.10000000149011612e");
+    assert_eq!(
+        Decimal::try_from(1e21_f64).unwrap().as_str(),
+        "1000000000000000000000"
+    );
+    assert!(Decimal::try_from(f64::NAN).is_err());
+    assert_eq!(Decimal::from(u128::MAX).scale(), 0);
+    assert_eq!(crate::from_str::<Decimal>("i7e").unwrap(), Decimal::from(7));
+}
+
+#[cfg(feature = "rust_decimal")]
+#[test]
+fn test_rust_decimal() {
+    let decimal: Decimal = "-1.50".parse().unwrap();
+    let exact = rust_decimal::Decimal::try_from(&decimal).unwrap();
+    assert_eq!(exact, rust_decimal::Decimal::new(-150, 2));
+    assert_eq!(Decimal::from(exact), decimal);
+    let too_long: Decimal = "0.1234567890123456789012345678901".parse().unwrap();
+    assert!(rust_decimal::Decimal::try_from(&too_long).is_err());
+}
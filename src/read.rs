@@ -0,0 +1,204 @@
+//! Input sources of the `Deserializer`.
+//!
+//! `SliceRead` borrows strings straight from the input, `IoRead` pulls bytes on
+//! demand from an `io::Read` and copies strings into a scratch buffer.
+
+use std::io;
+use std::ops::Deref;
+
+use crate::error::{ErrorKind, Result};
+
+/// Bytes that are either borrowed from the input or copied into a scratch buffer.
+pub enum Reference<'b, 'c, T: ?Sized> {
+    Borrowed(&'b T),
+    Copied(&'c T),
+}
+
+impl<T: ?Sized> Deref for Reference<'_, '_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            Reference::Borrowed(borrowed) => borrowed,
+            Reference::Copied(copied) => copied,
+        }
+    }
+}
+
+/// Source of bytes for the `Deserializer`. This trait is sealed.
+pub trait Read<'de>: private::Sealed {
+    /// Look at the next byte without consuming it, `None` at the end of input.
+    fn peek(&mut self) -> Result<Option<u8>>;
+
+    /// Consume the next byte, `None` at the end of input.
+    fn next(&mut self) -> Result<Option<u8>>;
+
+    /// Consume exactly `length` bytes, borrowing them from the input if possible.
+    fn read_bytes<'s>(
+        &'s mut self,
+        length: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>>;
+
+    /// Consume exactly `length` bytes without looking at them.
+    fn skip_bytes(&mut self, length: usize) -> Result<()>;
+
+    /// Number of bytes consumed so far.
+    fn offset(&self) -> usize;
+}
+
+/// Input that is already in memory.
+pub struct SliceRead<'a> {
+    slice: &'a [u8],
+    index: usize,
+}
+
+impl<'a> SliceRead<'a> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        SliceRead { slice, index: 0 }
+    }
+}
+
+impl<'a> Read<'a> for SliceRead<'a> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.slice.get(self.index).copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let ch = self.peek()?;
+        if ch.is_some() {
+            self.index += 1;
+        }
+        Ok(ch)
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        length: usize,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'a, 's, [u8]>> {
+        if self.slice.len() - self.index < length {
+            return Err(ErrorKind::Eof.into());
+        }
+        let bytes = &self.slice[self.index..self.index + length];
+        self.index += length;
+        Ok(Reference::Borrowed(bytes))
+    }
+
+    fn skip_bytes(&mut self, length: usize) -> Result<()> {
+        if self.slice.len() - self.index < length {
+            return Err(ErrorKind::Eof.into());
+        }
+        self.index += length;
+        Ok(())
+    }
+
+    fn offset(&self) -> usize {
+        self.index
+    }
+}
+
+/// Input that is pulled from an `io::Read` byte by byte, so wrap unbuffered
+/// sources such as `File` or `TcpStream` into an `io::BufReader`.
+pub struct IoRead<R> {
+    reader: R,
+    peeked: Option<u8>,
+    offset: usize,
+}
+
+impl<R> IoRead<R>
+where
+    R: io::Read,
+{
+    pub fn new(reader: R) -> Self {
+        IoRead {
+            reader,
+            peeked: None,
+            offset: 0,
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+}
+
+impl<'de, R> Read<'de> for IoRead<R>
+where
+    R: io::Read,
+{
+    fn peek(&mut self) -> Result<Option<u8>> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_byte()?;
+        }
+        Ok(self.peeked)
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let ch = match self.peeked.take() {
+            Some(ch) => Some(ch),
+            None => self.read_byte()?,
+        };
+        if ch.is_some() {
+            self.offset += 1;
+        }
+        Ok(ch)
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        length: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>> {
+        scratch.clear();
+        if length > 0 {
+            scratch.extend(self.peeked.take());
+        }
+        let remaining = (length - scratch.len()) as u64;
+        // `take` grows `scratch` as the bytes arrive instead of trusting a length
+        // prefix from the input with an allocation.
+        let mut reader = io::Read::take(&mut self.reader, remaining);
+        let read = io::Read::read_to_end(&mut reader, scratch);
+        self.offset += scratch.len();
+        read?;
+        if scratch.len() < length {
+            return Err(ErrorKind::Eof.into());
+        }
+        Ok(Reference::Copied(scratch))
+    }
+
+    fn skip_bytes(&mut self, length: usize) -> Result<()> {
+        let mut skipped = 0;
+        if length > 0 && self.peeked.take().is_some() {
+            skipped = 1;
+            self.offset += 1;
+        }
+        let mut reader = io::Read::take(&mut self.reader, (length - skipped) as u64);
+        let copied = io::copy(&mut reader, &mut io::sink())? as usize;
+        self.offset += copied;
+        if skipped + copied < length {
+            return Err(ErrorKind::Eof.into());
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::SliceRead<'_> {}
+
+    impl<R> Sealed for super::IoRead<R> where R: std::io::Read {}
+}
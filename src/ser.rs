@@ -1,39 +1,177 @@
-use crate::error::{Error, Result};
+use std::io;
+
+use crate::dialect::{Dialect, Literals};
+use crate::error::{Error, ErrorKind, Result};
+use crate::number::{payload_digits, BigInt, Decimal, BIG_INT_TOKEN, DECIMAL_TOKEN};
 use serde::{ser, Serialize};
 
-pub struct Serializer {
-    output: String,
+mod canonical;
+mod emitter;
+mod pretty;
+
+pub use self::canonical::write_canonical;
+pub use self::emitter::Emitter;
+pub use self::pretty::{reformat, to_string_pretty, PrettyPrinter};
+
+/// Representation of unit variants, see
+/// https://github.com/soramitsu/1code-java/issues/26.
+/// |   variant    |  rust   |  1coded   |
+/// | ------------ | ------- | --------- |
+/// | `String`     | E::Unit | 4:Unit    |
+/// | `Dictionary` | E::Unit | d4:UnitNe |
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitVariant {
+    #[default]
+    String,
+    Dictionary,
 }
 
-pub fn to_string<T>(value: &T) -> Result<String>
+pub struct Serializer<W> {
+    emitter: Emitter<W>,
+    unit_variant: UnitVariant,
+    canonical: bool,
+    dialect: Dialect,
+    /// Set for a buffered dictionary value, which bencode leaves out if it's null.
+    omit_null: bool,
+}
+
+impl<W> Serializer<W>
 where
-    T: Serialize,
+    W: io::Write,
 {
-    let mut serializer = Serializer {
-        output: String::new(),
-    };
+    pub fn new(writer: W) -> Self {
+        let mut emitter = Emitter::new(writer);
+        emitter.non_string_keys = true;
+        Serializer {
+            emitter,
+            unit_variant: UnitVariant::default(),
+            canonical: false,
+            dialect: Dialect::default(),
+            omit_null: false,
+        }
+    }
+
+    pub fn unit_variant(mut self, unit_variant: UnitVariant) -> Self {
+        self.unit_variant = unit_variant;
+        self
+    }
+
+    /// In canonical mode dictionary keys must be strings, they are written sorted
+    /// bytewise and duplicates are rejected, so equal values always give equal
+    /// bytes whatever the source container.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// In `Dialect::Bencode` dictionaries are always written sorted, as in
+    /// canonical mode.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Serializer with the same options that writes into a buffer.
+    fn buffer(&self) -> Serializer<Vec<u8>> {
+        Serializer::new(Vec::new())
+            .unit_variant(self.unit_variant)
+            .canonical(self.canonical)
+            .dialect(self.dialect)
+    }
+
+    fn serialize_to_buffer<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut buffer = self.buffer();
+        value.serialize(&mut buffer)?;
+        Ok(buffer.into_inner())
+    }
+
+    /// Raw bytes of a dictionary key, which must serialize to a string.
+    fn key_to_bytes<T>(&self, key: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut key = self.serialize_to_buffer(key)?;
+        let colon = key
+            .iter()
+            .position(|&ch| !ch.is_ascii_digit())
+            .filter(|&colon| colon > 0 && key[colon] == b':')
+            .ok_or(ErrorKind::KeyMustBeAString)?;
+        key.drain(..=colon);
+        Ok(key)
+    }
+
+    /// Unwraps the writer, e.g. to reuse its buffer for the next message.
+    pub fn into_inner(self) -> W {
+        self.emitter.into_inner()
+    }
+}
+
+/// Serializes `value` in canonical mode, see `Serializer::canonical`.
+pub fn to_vec_canonical<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(Vec::new()).canonical(true);
     value.serialize(&mut serializer)?;
-    Ok(serializer.output)
+    Ok(serializer.into_inner())
+}
+
+/// Output isn't buffered, so wrap unbuffered sinks such as `File` or `TcpStream`
+/// into an `io::BufWriter`.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(writer);
+    value.serialize(&mut serializer)
+}
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = Vec::new();
+    to_writer(&mut output, value)?;
+    Ok(output)
+}
+
+/// Fails with `ErrorKind::InvalidUtf8` if byte strings in `value` aren't UTF-8, use
+/// `to_vec` for them.
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    String::from_utf8(to_vec(value)?).map_err(|_| ErrorKind::InvalidUtf8.into())
 }
 
-impl<'a> ser::Serializer for &'a mut Serializer {
+impl<'a, W> ser::Serializer for &'a mut Serializer<W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
     type SerializeSeq = Self;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
-    type SerializeStruct = Self;
-    type SerializeStructVariant = Self;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
 
     /// | rust  | 1coded |
     /// | ----- | ------ |
     /// | true  |   T    |
     /// | false |   F    |
     fn serialize_bool(self, value: bool) -> Result<()> {
-        self.output += if value { "T" } else { "F" };
-        Ok(())
+        match self.dialect {
+            Dialect::Onecode => self.emitter.bool(value),
+            Dialect::Bencode(Literals::Integers) => self.emitter.int(i64::from(value)),
+            Dialect::Bencode(Literals::Reject) => Err(ErrorKind::NotBencode("boolean").into()),
+        }
     }
 
     /// | rust  | 1coded |
@@ -43,9 +181,11 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     /// | -1    | i-1e   |
     /// | 1.5   | i1.5e  |
     fn serialize_i64(self, value: i64) -> Result<()> {
-        //TODO: replace with usage of https://crates.io/crates/itoa
-        self.output += &format!("i{}e", value.to_string());
-        Ok(())
+        self.emitter.int(value)
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.emitter.int128(value)
     }
 
     fn serialize_i32(self, value: i32) -> Result<()> {
@@ -67,9 +207,11 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     /// | -1    | i-1e   |
     /// | 1.5   | i1.5e  |
     fn serialize_u64(self, value: u64) -> Result<()> {
-        //TODO: replace with usage of https://crates.io/crates/itoa
-        self.output += &format!("i{}e", value.to_string());
-        Ok(())
+        self.emitter.uint(value)
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.emitter.uint128(value)
     }
 
     fn serialize_u32(self, value: u32) -> Result<()> {
@@ -91,9 +233,10 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     /// | -1    | i-1e   |
     /// | 1.5   | i1.5e  |
     fn serialize_f64(self, value: f64) -> Result<()> {
-        //TODO: replace with usage of https://crates.io/crates/itoa
-        self.output += &format!("i{}e", value.to_string());
-        Ok(())
+        if let Dialect::Bencode(_) = self.dialect {
+            return Err(ErrorKind::NotBencode("float").into());
+        }
+        self.emitter.float(value)
     }
 
     fn serialize_f32(self, value: f32) -> Result<()> {
@@ -106,29 +249,25 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     /// | "0.1" | 3:0.1  |
     /// | "h h" | 3:h h  |
     fn serialize_str(self, value: &str) -> Result<()> {
-        self.output += &format!("{}:{}", value.len(), value);
-        Ok(())
+        self.emitter.str(value)
     }
 
     fn serialize_char(self, value: char) -> Result<()> {
         self.serialize_str(&value.to_string())
     }
 
-    /// |    rust     |    1coded   |
-    /// | ----------- | ----------- |
-    /// | vec![1,2,3] | li1ei2ei3ee |
+    /// Byte strings share the string form, bytes are written as is.
+    /// |  rust  | 1coded |
+    /// | ------ | ------ |
+    /// | b""    | 0:     |
+    /// | b"abc" | 3:abc  |
     fn serialize_bytes(self, value: &[u8]) -> Result<()> {
-        use serde::ser::SerializeSeq;
-        let mut seq = self.serialize_seq(Some(value.len()))?;
-        for byte in value {
-            seq.serialize_element(byte)?;
-        }
-        seq.end()
+        self.emitter.bytes(value)
     }
 
     /// List serialization: start.
     fn serialize_seq(self, _length: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.output += "l";
+        self.emitter.begin_list()?;
         Ok(self)
     }
 
@@ -157,16 +296,15 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.output += "d";
-        variant.serialize(&mut *self)?;
+        self.emitter.begin_dict()?;
+        self.emitter.key(variant)?;
         value.serialize(&mut *self)?;
-        self.output += "e";
-        Ok(())
+        self.emitter.end()
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.output += "d";
-        Ok(self)
+        self.emitter.begin_dict()?;
+        Ok(Compound::new(self))
     }
 
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
@@ -180,10 +318,10 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.output += "d";
-        variant.serialize(&mut *self)?;
-        self.output += "d";
-        Ok(self)
+        self.emitter.begin_dict()?;
+        self.emitter.key(variant)?;
+        self.emitter.begin_dict()?;
+        Ok(Compound::new(self))
     }
 
     fn serialize_tuple_variant(
@@ -193,9 +331,9 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.output += "d";
-        variant.serialize(&mut *self)?;
-        self.output += "l";
+        self.emitter.begin_dict()?;
+        self.emitter.key(variant)?;
+        self.emitter.begin_list()?;
         Ok(self)
     }
 
@@ -204,8 +342,14 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     /// | ()           | N      |
     /// | Option::None | N      |
     fn serialize_unit(self) -> Result<()> {
-        self.output += "N";
-        Ok(())
+        match self.dialect {
+            Dialect::Onecode => self.emitter.null(),
+            // Nothing is written, so `Compound` drops the entry.
+            Dialect::Bencode(Literals::Integers) if self.omit_null && self.emitter.depth() == 0 => {
+                Ok(())
+            }
+            Dialect::Bencode(_) => Err(ErrorKind::NotBencode("null").into()),
+        }
     }
 
     fn serialize_none(self) -> Result<()> {
@@ -223,25 +367,52 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self.serialize_unit()
     }
 
+    /// Depends on the chosen `UnitVariant`.
+    /// |  rust   |  1coded   |
+    /// | ------- | --------- |
+    /// | E::Unit | 4:Unit    |
+    /// | E::Unit | d4:UnitNe |
     fn serialize_unit_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<()> {
-        //TODO decide https://github.com/soramitsu/1code-java/issues/26
-        self.serialize_str(variant)
+        match self.unit_variant {
+            UnitVariant::String => self.serialize_str(variant),
+            UnitVariant::Dictionary => {
+                self.emitter.begin_dict()?;
+                self.emitter.key(variant)?;
+                ser::Serializer::serialize_unit(&mut *self)?;
+                self.emitter.end()
+            }
+        }
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    /// `BigInt` and `Decimal` are written as numbers from the digits they carry.
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if name == BIG_INT_TOKEN {
+            let big_int: BigInt = payload_digits(value)?.parse()?;
+            return self.emitter.big_int(&big_int);
+        }
+        if name == DECIMAL_TOKEN {
+            if let Dialect::Bencode(_) = self.dialect {
+                return Err(ErrorKind::NotBencode("decimal").into());
+            }
+            let decimal: Decimal = payload_digits(value)?.parse()?;
+            return self.emitter.decimal(&decimal);
+        }
         value.serialize(self)
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl<W> ser::SerializeSeq for &mut Serializer<W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -253,12 +424,14 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     }
 
     fn end(self) -> Result<()> {
-        self.output += "e";
-        Ok(())
+        self.emitter.end()
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl<W> ser::SerializeTuple for &mut Serializer<W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -270,12 +443,14 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
     }
 
     fn end(self) -> Result<()> {
-        self.output += "e";
-        Ok(())
+        self.emitter.end()
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl<W> ser::SerializeTupleStruct for &mut Serializer<W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -287,12 +462,14 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     }
 
     fn end(self) -> Result<()> {
-        self.output += "e";
-        Ok(())
+        self.emitter.end()
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+impl<W> ser::SerializeTupleVariant for &mut Serializer<W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -304,12 +481,72 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
     }
 
     fn end(self) -> Result<()> {
-        self.output += "ee";
+        self.emitter.end()?;
+        self.emitter.end()
+    }
+}
+
+/// Dictionary that is written as it arrives or, in canonical mode and bencode,
+/// buffered and written sorted by key when it ends.
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    entries: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+    key: Option<Vec<u8>>,
+}
+
+impl<'a, W> Compound<'a, W>
+where
+    W: io::Write,
+{
+    fn new(ser: &'a mut Serializer<W>) -> Self {
+        let entries = if ser.canonical || ser.dialect != Dialect::Onecode {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        Compound {
+            ser,
+            entries,
+            key: None,
+        }
+    }
+
+    fn serialize_entry_value<T>(&mut self, key: Vec<u8>, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut buffer = self.ser.buffer();
+        buffer.omit_null = true;
+        value.serialize(&mut buffer)?;
+        let value = buffer.into_inner();
+        // Only a null that bencode leaves out writes nothing.
+        if let (Some(entries), false) = (&mut self.entries, value.is_empty()) {
+            entries.push((key, value));
+        }
         Ok(())
     }
+
+    /// Writes the buffered entries of canonical mode and the end of dictionary.
+    fn end_dictionary(self) -> Result<&'a mut Serializer<W>> {
+        if let Some(mut entries) = self.entries {
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                return Err(ErrorKind::DuplicateKey.into());
+            }
+            for (key, value) in entries {
+                self.ser.emitter.key_bytes(&key)?;
+                self.ser.emitter.raw(&value)?;
+            }
+        }
+        self.ser.emitter.end()?;
+        Ok(self.ser)
+    }
 }
 
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl<W> ser::SerializeMap for Compound<'_, W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -317,23 +554,33 @@ impl<'a> ser::SerializeMap for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        key.serialize(&mut **self)
+        if self.entries.is_some() {
+            self.key = Some(self.ser.key_to_bytes(key)?);
+            Ok(())
+        } else {
+            key.serialize(&mut *self.ser)
+        }
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        match self.key.take() {
+            Some(key) => self.serialize_entry_value(key, value),
+            None => value.serialize(&mut *self.ser),
+        }
     }
 
     fn end(self) -> Result<()> {
-        self.output += "e";
-        Ok(())
+        self.end_dictionary().map(drop)
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut Serializer {
+impl<W> ser::SerializeStruct for Compound<'_, W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -341,17 +588,23 @@ impl<'a> ser::SerializeStruct for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        key.serialize(&mut **self)?;
-        value.serialize(&mut **self)
+        if self.entries.is_some() {
+            self.serialize_entry_value(key.as_bytes().to_vec(), value)
+        } else {
+            self.ser.emitter.key(key)?;
+            value.serialize(&mut *self.ser)
+        }
     }
 
     fn end(self) -> Result<()> {
-        self.output += "e";
-        Ok(())
+        self.end_dictionary().map(drop)
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
+impl<W> ser::SerializeStructVariant for Compound<'_, W>
+where
+    W: io::Write,
+{
     type Ok = ();
     type Error = Error;
 
@@ -359,17 +612,17 @@ impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        key.serialize(&mut **self)?;
-        value.serialize(&mut **self)
+        ser::SerializeStruct::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "ee";
-        Ok(())
+        self.end_dictionary()?.emitter.end()
     }
 }
 
+#[cfg(test)]
 use std::collections::HashMap;
+#[cfg(test)]
 #[derive(serde::Serialize)]
 struct StructToSerialize {
     boolean: bool,
@@ -391,6 +644,130 @@ struct StructToSerialize {
     null: Option<()>,
 }
 
+#[test]
+fn test_i128() {
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Ledger {
+        credit: u128,
+        debit: i128,
+        total: crate::BigInt,
+    }
+
+    let ledger = Ledger {
+        credit: u128::MAX,
+        debit: i128::MIN,
+        total: "1000000000000000000000000000000000000000".parse().unwrap(),
+    };
+    let expected = "d6:crediti340282366920938463463374607431768211455e5:debiti-170141183460469231731687303715884105728e5:totali1000000000000000000000000000000000000000ee";
+    assert_eq!(to_string(&ledger).unwrap(), expected);
+    assert_eq!(crate::from_str::<Ledger>(expected).unwrap(), ledger);
+}
+
+#[test]
+fn test_bytes() {
+    #[derive(serde::Serialize)]
+    struct Test<'a> {
+        #[serde(with = "serde_bytes")]
+        hash: &'a [u8],
+    }
+    let test = Test {
+        hash: b"\x01\x02\x03",
+    };
+    assert_eq!(to_string(&test).unwrap(), "d4:hash3:\x01\x02\x03e");
+    let test = Test { hash: b"\xff" };
+    assert_eq!(
+        &ErrorKind::InvalidUtf8,
+        to_string(&test).unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_writer() {
+    let mut output = Vec::new();
+    to_writer(&mut output, &vec![1, 2]).unwrap();
+    to_writer(&mut output, "abc").unwrap();
+    assert_eq!(output, b"li1ei2ee3:abc");
+
+    let mut serializer = Serializer::new(output);
+    (-1, 'a').serialize(&mut serializer).unwrap();
+    let mut output = serializer.into_inner();
+    assert_eq!(output, b"li1ei2ee3:abcli-1e1:ae");
+    output.clear();
+    to_writer(&mut output, &Some(())).unwrap();
+    assert_eq!(output, b"N");
+
+    assert_eq!(to_vec(&[0xff_u8][..]).unwrap(), b"li255ee");
+}
+
+#[test]
+fn test_enum() {
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Newtype(u32),
+        Tuple(u32, u32),
+        Struct { a: u32 },
+    }
+
+    let values = vec![E::Unit, E::Newtype(1), E::Tuple(1, 2), E::Struct { a: 1 }];
+    let expected = "l4:Unitd7:Newtypei1eed5:Tupleli1ei2eeed6:Structd1:ai1eeee";
+    assert_eq!(to_string(&values).unwrap(), expected);
+    assert_eq!(crate::from_str::<Vec<E>>(expected).unwrap(), values);
+
+    let mut serializer = Serializer::new(Vec::new()).unit_variant(UnitVariant::Dictionary);
+    values.serialize(&mut serializer).unwrap();
+    let output = serializer.into_inner();
+    let expected = "ld4:UnitNed7:Newtypei1eed5:Tupleli1ei2eeed6:Structd1:ai1eeee";
+    assert_eq!(output, expected.as_bytes());
+    assert_eq!(crate::from_slice::<Vec<E>>(&output).unwrap(), values);
+}
+
+#[test]
+fn test_canonical() {
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    struct Test {
+        b: u8,
+        a: HashMap<String, u8>,
+    }
+    let mut hash_map = HashMap::new();
+    let mut btree_map = BTreeMap::new();
+    for (i, key) in ["b", "a", "2", "10", "Z", "", "aa", "я"].iter().enumerate() {
+        hash_map.insert(key.to_string(), i as u8);
+        btree_map.insert(key.to_string(), i as u8);
+    }
+    let expected = "d0:i5e2:10i3e1:2i2e1:Zi4e1:ai1e2:aai6e1:bi0e2:яi7ee";
+    assert_eq!(to_vec_canonical(&hash_map).unwrap(), expected.as_bytes());
+    assert_eq!(to_vec_canonical(&btree_map).unwrap(), expected.as_bytes());
+
+    let test = Test { b: 1, a: hash_map };
+    let expected = format!("d1:a{}1:bi1ee", expected);
+    assert_eq!(to_vec_canonical(&test).unwrap(), expected.as_bytes());
+
+    struct Duplicates;
+    impl Serialize for Duplicates {
+        fn serialize<S: ser::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_map(vec![("a", 1), ("b", 2), ("a", 3)])
+        }
+    }
+    assert_eq!(to_string(&Duplicates).unwrap(), "d1:ai1e1:bi2e1:ai3ee");
+    assert_eq!(
+        &ErrorKind::DuplicateKey,
+        to_vec_canonical(&Duplicates).unwrap_err().kind()
+    );
+
+    let mut integer_keys = HashMap::new();
+    integer_keys.insert(1, 1);
+    assert_eq!(
+        &ErrorKind::KeyMustBeAString,
+        to_vec_canonical(&integer_keys).unwrap_err().kind()
+    );
+}
+
 // Transforms struct into
 /*
     d
@@ -435,9 +812,9 @@ fn it_works() {
         japan_string: "こんにちは世界".to_string(),
         number_list: vec![1, 2, 3],
         string_list: vec!["1".to_string(), "01".to_string(), "011".to_string()],
-        number_dictionary: number_dictionary,
-        string_dictionary: string_dictionary,
-        list_dictionary: list_dictionary,
+        number_dictionary,
+        string_dictionary,
+        list_dictionary,
         null: Option::None,
     };
     let expected_result = "d7:booleanT16:positive_integeri1e16:negative_integeri-1e14:positive_floati1.5e14:negative_floati-1.5e20:negative_float_commai-1.5e12:empty_string0:13:number_string3:0.112:latin_string11:hello world15:cyrillic_string19:привет мир12:japan_string21:こんにちは世界11:number_listli1ei2ei3ee11:string_listl1:12:013:011e17:number_dictionaryd1:1i1ee17:string_dictionaryd1:11:1e15:list_dictionaryd1:1li1ei2ei3eee4:nullNe";
@@ -0,0 +1,173 @@
+//! Canonical form of a document that is read token by token, without serde.
+
+use std::io;
+
+use crate::de::{Event, Tokenizer};
+use crate::error::{Error, ErrorKind, Result};
+use crate::read;
+
+/// A list, or a dictionary with its entries, that isn't closed yet.
+enum Frame {
+    List(Vec<u8>),
+    Dict {
+        entries: Vec<Entry>,
+        /// Key that waits for its value.
+        key: Option<Entry>,
+    },
+}
+
+/// Raw key bytes with its offset, and the encoded key and value.
+struct Entry {
+    key: Vec<u8>,
+    offset: usize,
+    encoded: Vec<u8>,
+}
+
+/// Reads the next value from `tokenizer` and writes it with the dictionary keys
+/// sorted bytewise. Numbers keep all their significant digits and lose only the
+/// zeros that strict mode rejects, so no value changes on the way, unlike with
+/// `Value` which has no decimals. Non-string keys fail with `ErrorKind::KeyMustBeAString` and
+/// repeated ones with `ErrorKind::DuplicateKey`.
+pub fn write_canonical<'de, R, W>(tokenizer: &mut Tokenizer<R>, mut writer: W) -> Result<()>
+where
+    R: read::Read<'de>,
+    W: io::Write,
+{
+    let mut stack = Vec::new();
+    while let Some(token) = tokenizer.next_token()? {
+        let is_key = matches!(stack.last(), Some(Frame::Dict { key: None, .. }));
+        let encoded = match token.event {
+            Event::Str(bytes) if is_key => {
+                stack_key(&mut stack, bytes, token.offset);
+                continue;
+            }
+            Event::ListStart | Event::DictStart | Event::Int(_) | Event::Bool(_) | Event::Null
+                if is_key =>
+            {
+                return Err(Error::from(ErrorKind::KeyMustBeAString).fix_offset(token.offset))
+            }
+            Event::ListStart => {
+                stack.push(Frame::List(b"l".to_vec()));
+                continue;
+            }
+            Event::DictStart => {
+                stack.push(Frame::Dict {
+                    entries: Vec::new(),
+                    key: None,
+                });
+                continue;
+            }
+            Event::End => match stack.pop() {
+                Some(Frame::List(mut list)) => {
+                    list.push(b'e');
+                    list
+                }
+                Some(Frame::Dict { entries, .. }) => close_dict(entries)?,
+                // The tokenizer balances lists and dictionaries.
+                None => return Err(ErrorKind::InvalidNesting.into()),
+            },
+            Event::Int(number) => format!("i{}e", canonical_number(number)).into_bytes(),
+            Event::Str(bytes) => encode_str(bytes),
+            Event::Bool(true) => b"T".to_vec(),
+            Event::Bool(false) => b"F".to_vec(),
+            Event::Null => b"N".to_vec(),
+        };
+        match stack.last_mut() {
+            Some(Frame::List(list)) => list.extend_from_slice(&encoded),
+            Some(Frame::Dict { entries, key }) => {
+                // A key is waiting, otherwise the value would have been a key.
+                if let Some(mut entry) = key.take() {
+                    entry.encoded.extend_from_slice(&encoded);
+                    entries.push(entry);
+                }
+            }
+            None => writer.write_all(&encoded)?,
+        }
+    }
+    Ok(())
+}
+
+fn stack_key(stack: &mut [Frame], bytes: &[u8], offset: usize) {
+    if let Some(Frame::Dict { key, .. }) = stack.last_mut() {
+        *key = Some(Entry {
+            key: bytes.to_vec(),
+            offset,
+            encoded: encode_str(bytes),
+        });
+    }
+}
+
+/// Drops leading zeros, trailing zeros of the fraction and the sign of zero.
+fn canonical_number(number: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let mut parts = unsigned.splitn(2, '.');
+    let integer = parts.next().unwrap_or_default().trim_start_matches('0');
+    let fraction = parts.next().unwrap_or_default().trim_end_matches('0');
+    let integer = if integer.is_empty() { "0" } else { integer };
+    match (integer, fraction) {
+        ("0", "") => "0".to_owned(),
+        (integer, "") => format!("{}{}", sign, integer),
+        (integer, fraction) => format!("{}{}.{}", sign, integer, fraction),
+    }
+}
+
+fn encode_str(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = format!("{}:", bytes.len()).into_bytes();
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+/// Sorts the entries, the first repeated key fails at its second occurrence.
+fn close_dict(mut entries: Vec<Entry>) -> Result<Vec<u8>> {
+    entries.sort_by(|a, b| a.key.cmp(&b.key).then(a.offset.cmp(&b.offset)));
+    if let Some(pair) = entries.windows(2).find(|pair| pair[0].key == pair[1].key) {
+        return Err(Error::from(ErrorKind::DuplicateKey).fix_offset(pair[1].offset));
+    }
+    let mut dict = b"d".to_vec();
+    for entry in entries {
+        dict.extend_from_slice(&entry.encoded);
+    }
+    dict.push(b'e');
+    Ok(dict)
+}
+
+#[test]
+fn test_write_canonical() {
+    fn canonical(input: &[u8]) -> Result<Vec<u8>> {
+        let mut tokenizer = Tokenizer::from_slice(input);
+        let mut output = Vec::new();
+        write_canonical(&mut tokenizer, &mut output)?;
+        tokenizer.end()?;
+        Ok(output)
+    }
+
+    assert_eq!(
+        canonical(b"d1:bld10:zzzzzzzzzz1:x1:yNee1:ai0.1000000000000000000001ee").unwrap(),
+        b"d1:ai0.1000000000000000000001e1:bld1:yN10:zzzzzzzzzz1:xeee"
+    );
+    assert_eq!(
+        canonical(b"d25:$onecode::private::BigInt3:123e").unwrap(),
+        b"d25:$onecode::private::BigInt3:123e"
+    );
+    assert_eq!(
+        canonical(b"li007ei1.50ei-0.0ei-00.010ei2.0e03:abce").unwrap(),
+        b"li7ei1.5ei0ei-0.01ei2e3:abce"
+    );
+    assert_eq!(
+        canonical(b"d2:\xff\xffN1:aTe").unwrap(),
+        b"d1:aT2:\xff\xffNe"
+    );
+    let error = canonical(b"d1:aNi1eNe").unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::KeyMustBeAString, Some(5))
+    );
+    let error = canonical(b"d1:bN1:aN1:bTe").unwrap_err();
+    assert_eq!(
+        (error.kind(), error.offset()),
+        (&ErrorKind::DuplicateKey, Some(9))
+    );
+}
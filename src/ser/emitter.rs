@@ -0,0 +1,285 @@
+//! Push writer that builds a document call by call, without serde.
+
+use std::io;
+
+use crate::error::{ErrorKind, Result};
+use crate::number::{BigInt, Decimal};
+
+/// A list or dictionary that isn't closed yet.
+enum Frame {
+    List,
+    /// Whether the next call must be a key rather than a value.
+    Dict {
+        key: bool,
+    },
+}
+
+/// Writes tokens into any sink, checking that every call fits the lists and
+/// dictionaries that are open. `Serializer` is built on it.
+/// |                    calls                    |  1coded   |
+/// | ------------------------------------------- | --------- |
+/// | begin_list(), int(-1), null(), end()        | li-1eNe   |
+/// | begin_dict(), key("a"), bytes(b"xy"), end() | d1:a2:xye |
+///
+/// A misplaced call fails with `ErrorKind::InvalidNesting`, a value where a key
+/// is expected with `ErrorKind::KeyMustBeAString`. Values are written one after
+/// another, so a stream of several documents can be emitted too.
+pub struct Emitter<W> {
+    writer: W,
+    stack: Vec<Frame>,
+    /// `Serializer` writes keys of any type, like the non-strict `Deserializer` reads them.
+    pub(crate) non_string_keys: bool,
+}
+
+impl<W> Emitter<W>
+where
+    W: io::Write,
+{
+    pub fn new(writer: W) -> Self {
+        Emitter {
+            writer,
+            stack: Vec::new(),
+            non_string_keys: false,
+        }
+    }
+
+    /// Number of lists and dictionaries that are open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Unwraps the writer, whether or not every list and dictionary was closed.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Unwraps the writer after checking that every list and dictionary was closed.
+    pub fn finish(self) -> Result<W> {
+        if !self.stack.is_empty() {
+            return Err(ErrorKind::InvalidNesting.into());
+        }
+        Ok(self.writer)
+    }
+
+    pub fn begin_list(&mut self) -> Result<()> {
+        self.value()?;
+        self.stack.push(Frame::List);
+        self.write(b"l")
+    }
+
+    pub fn begin_dict(&mut self) -> Result<()> {
+        self.value()?;
+        self.stack.push(Frame::Dict { key: true });
+        self.write(b"d")
+    }
+
+    /// Closes the innermost list or dictionary, which can't be left with a key
+    /// that has no value.
+    pub fn end(&mut self) -> Result<()> {
+        match self.stack.last() {
+            Some(Frame::List) | Some(Frame::Dict { key: true }) => {}
+            _ => return Err(ErrorKind::InvalidNesting.into()),
+        }
+        self.stack.pop();
+        self.write(b"e")
+    }
+
+    pub fn key(&mut self, key: &str) -> Result<()> {
+        self.key_bytes(key.as_bytes())
+    }
+
+    pub fn key_bytes(&mut self, key: &[u8]) -> Result<()> {
+        match self.stack.last_mut() {
+            Some(Frame::Dict {
+                key: expected @ true,
+            }) => *expected = false,
+            _ => return Err(ErrorKind::InvalidNesting.into()),
+        }
+        self.write_bytes(key)
+    }
+
+    pub fn null(&mut self) -> Result<()> {
+        self.value()?;
+        self.write(b"N")
+    }
+
+    pub fn bool(&mut self, value: bool) -> Result<()> {
+        self.value()?;
+        self.write(if value { b"T" } else { b"F" })
+    }
+
+    pub fn int(&mut self, value: i64) -> Result<()> {
+        self.number(value)
+    }
+
+    pub fn uint(&mut self, value: u64) -> Result<()> {
+        self.number(value)
+    }
+
+    pub fn int128(&mut self, value: i128) -> Result<()> {
+        self.number(value)
+    }
+
+    pub fn uint128(&mut self, value: u128) -> Result<()> {
+        self.number(value)
+    }
+
+    /// Fails with `ErrorKind::NonFiniteFloat` for NaN and infinities.
+    pub fn float(&mut self, value: f64) -> Result<()> {
+        if !value.is_finite() {
+            return Err(ErrorKind::NonFiniteFloat.into());
+        }
+        self.number(value)
+    }
+
+    pub fn big_int(&mut self, value: &BigInt) -> Result<()> {
+        self.number(value)
+    }
+
+    pub fn decimal(&mut self, value: &Decimal) -> Result<()> {
+        self.number(value)
+    }
+
+    pub fn str(&mut self, value: &str) -> Result<()> {
+        self.bytes(value.as_bytes())
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) -> Result<()> {
+        self.value()?;
+        self.write_bytes(value)
+    }
+
+    /// A value that is already encoded, such as the buffered entries of canonical mode.
+    pub(crate) fn raw(&mut self, value: &[u8]) -> Result<()> {
+        self.value()?;
+        self.write(value)
+    }
+
+    /// Accounts for the value that is about to be written.
+    fn value(&mut self) -> Result<()> {
+        match self.stack.last_mut() {
+            Some(Frame::Dict { key: true }) if !self.non_string_keys => {
+                Err(ErrorKind::KeyMustBeAString.into())
+            }
+            Some(Frame::Dict { key }) => {
+                *key = !*key;
+                Ok(())
+            }
+            Some(Frame::List) | None => Ok(()),
+        }
+    }
+
+    fn number<T: std::fmt::Display>(&mut self, value: T) -> Result<()> {
+        self.value()?;
+        //TODO: replace with usage of https://crates.io/crates/itoa
+        write!(self.writer, "i{}e", value)?;
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, value: &[u8]) -> Result<()> {
+        write!(self.writer, "{}:", value.len())?;
+        self.write(value)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_emitter() {
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.begin_dict().unwrap();
+    emitter.key("a").unwrap();
+    emitter.begin_list().unwrap();
+    emitter.int(-1).unwrap();
+    emitter.uint(u64::MAX).unwrap();
+    emitter.float(1.5).unwrap();
+    emitter.null().unwrap();
+    emitter.bool(true).unwrap();
+    emitter
+        .big_int(&"11111111111111111111".parse().unwrap())
+        .unwrap();
+    emitter.decimal(&"0.10".parse().unwrap()).unwrap();
+    emitter.end().unwrap();
+    assert_eq!(emitter.depth(), 1);
+    emitter.key_bytes(b"b\xff").unwrap();
+    emitter.bytes(b"\x00").unwrap();
+    emitter.key("c").unwrap();
+    emitter.begin_dict().unwrap();
+    emitter.end().unwrap();
+    emitter.end().unwrap();
+    emitter.str("x").unwrap();
+    let output = emitter.finish().unwrap();
+    assert_eq!(
+        output,
+        &b"d1:ali-1ei18446744073709551615ei1.5eNTi11111111111111111111ei0.10ee2:b\xff1:\x001:cdee1:x"[..]
+    );
+
+    fn error(calls: impl FnOnce(&mut Emitter<Vec<u8>>) -> Result<()>) -> crate::Error {
+        let mut emitter = Emitter::new(Vec::new());
+        calls(&mut emitter)
+            .and_then(|()| emitter.finish().map(drop))
+            .unwrap_err()
+    }
+
+    assert_eq!(
+        &ErrorKind::InvalidNesting,
+        error(|emitter| emitter.end()).kind()
+    );
+    assert_eq!(
+        &ErrorKind::InvalidNesting,
+        error(|emitter| emitter.key("a")).kind()
+    );
+    assert_eq!(
+        &ErrorKind::InvalidNesting,
+        error(|emitter| emitter.begin_list()).kind()
+    );
+    assert_eq!(
+        &ErrorKind::InvalidNesting,
+        error(|emitter| {
+            emitter.begin_list()?;
+            emitter.key("a")
+        })
+        .kind()
+    );
+    assert_eq!(
+        &ErrorKind::InvalidNesting,
+        error(|emitter| {
+            emitter.begin_dict()?;
+            emitter.key("a")?;
+            emitter.key("b")
+        })
+        .kind()
+    );
+    assert_eq!(
+        &ErrorKind::InvalidNesting,
+        error(|emitter| {
+            emitter.begin_dict()?;
+            emitter.key("a")?;
+            emitter.end()
+        })
+        .kind()
+    );
+    assert_eq!(
+        &ErrorKind::KeyMustBeAString,
+        error(|emitter| {
+            emitter.begin_dict()?;
+            emitter.int(1)
+        })
+        .kind()
+    );
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let mut emitter = Emitter::new(Vec::new());
+        assert_eq!(
+            &ErrorKind::NonFiniteFloat,
+            emitter.float(value).unwrap_err().kind()
+        );
+        assert!(emitter.into_inner().is_empty());
+        assert_eq!(
+            &ErrorKind::NonFiniteFloat,
+            crate::to_vec(&vec![value]).unwrap_err().kind()
+        );
+    }
+}
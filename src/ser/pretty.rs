@@ -0,0 +1,345 @@
+//! Human-readable layout of documents. It is a separate format for people to
+//! read, not 1code: the whitespace between tokens is rejected unless
+//! `Deserializer::whitespace` is on, and never in strict mode.
+
+use std::io;
+
+use serde::Serialize;
+
+use crate::de::{Event, Tokenizer};
+use crate::error::{ErrorKind, Result};
+use crate::read;
+
+/// Options of the pretty printer. Dictionaries get one entry per line, lists
+/// that fit into the line width are packed into one line:
+/// ```text
+/// d
+///   4:name 5:Alice
+///   6:scores l i1e i2e i3e e
+///   4:tags l
+///     9:long text
+///     ...
+///   e
+/// e
+/// ```
+/// The output isn't 1code, tokens are separated by whitespace. `reformat` and
+/// `Deserializer::whitespace` read it back, `ser::write_canonical` turns it
+/// into 1code again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrettyPrinter {
+    indent: usize,
+    width: usize,
+    color: bool,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        PrettyPrinter {
+            indent: 2,
+            width: 80,
+            color: false,
+        }
+    }
+}
+
+/// A parsed value, with every token already encoded.
+enum Node {
+    Token(Kind, Vec<u8>),
+    List(Vec<Node>),
+    Dict(Vec<(Node, Node)>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Str,
+    Bool,
+    Null,
+}
+
+/// ANSI colours of the token types.
+const STRUCTURE: &str = "\x1b[1m";
+const KEY: &str = "\x1b[34m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const LITERAL: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+impl PrettyPrinter {
+    pub fn new() -> Self {
+        PrettyPrinter::default()
+    }
+
+    /// Spaces per nesting level.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Line width in bytes that packed lists must fit into, `0` puts every list
+    /// element on its own line.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Colour tokens by type with ANSI escape codes, for terminals. The output
+    /// isn't valid 1code then.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Pretty-prints `value`, see `ser::to_string` about byte strings.
+    pub fn to_string<T>(&self, value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        let output = crate::ser::to_vec(value)?;
+        let mut pretty = Vec::new();
+        self.write(&mut Tokenizer::from_slice(&output), &mut pretty)?;
+        String::from_utf8(pretty).map_err(|_| ErrorKind::InvalidUtf8.into())
+    }
+
+    /// Lays out a document that is compact or pretty-printed already.
+    pub fn reformat(&self, input: &str) -> Result<String> {
+        let mut tokenizer = Tokenizer::from_str(input).whitespace(true);
+        let mut pretty = Vec::new();
+        self.write(&mut tokenizer, &mut pretty)?;
+        tokenizer.end()?;
+        // Tokens are copied from `input` and everything else is ASCII.
+        String::from_utf8(pretty).map_err(|_| ErrorKind::InvalidUtf8.into())
+    }
+
+    /// Reads the next value from `tokenizer` and writes it pretty-printed.
+    pub fn write<'de, R, W>(&self, tokenizer: &mut Tokenizer<R>, mut writer: W) -> Result<()>
+    where
+        R: read::Read<'de>,
+        W: io::Write,
+    {
+        let node = read_node(tokenizer)?.ok_or(ErrorKind::Eof)?;
+        self.write_node(&mut writer, &node, 0, 0, false)?;
+        Ok(())
+    }
+
+    /// Writes `node` that starts at `column` of a line indented by `level`.
+    fn write_node<W: io::Write>(
+        &self,
+        writer: &mut W,
+        node: &Node,
+        level: usize,
+        column: usize,
+        key: bool,
+    ) -> io::Result<()> {
+        match node {
+            Node::Token(kind, token) => {
+                let color = match kind {
+                    Kind::Str if key => KEY,
+                    Kind::Str => STRING,
+                    Kind::Int => NUMBER,
+                    Kind::Bool | Kind::Null => LITERAL,
+                };
+                self.write_token(writer, color, token)
+            }
+            Node::List(list) if list.is_empty() => self.write_token(writer, STRUCTURE, b"le"),
+            Node::Dict(dict) if dict.is_empty() => self.write_token(writer, STRUCTURE, b"de"),
+            Node::List(list) => {
+                let packed = flat_width(node).filter(|width| column + width <= self.width);
+                self.write_token(writer, STRUCTURE, b"l")?;
+                for element in list {
+                    if packed.is_some() {
+                        writer.write_all(b" ")?;
+                        self.write_node(writer, element, level, column, false)?;
+                    } else {
+                        self.write_newline(writer, level + 1)?;
+                        let column = (level + 1) * self.indent;
+                        self.write_node(writer, element, level + 1, column, false)?;
+                    }
+                }
+                if packed.is_some() {
+                    writer.write_all(b" ")?;
+                } else {
+                    self.write_newline(writer, level)?;
+                }
+                self.write_token(writer, STRUCTURE, b"e")
+            }
+            Node::Dict(dict) => {
+                self.write_token(writer, STRUCTURE, b"d")?;
+                for (key, value) in dict {
+                    self.write_newline(writer, level + 1)?;
+                    let column = (level + 1) * self.indent;
+                    self.write_node(writer, key, level + 1, column, true)?;
+                    writer.write_all(b" ")?;
+                    let column = column + flat_width(key).unwrap_or(0) + 1;
+                    self.write_node(writer, value, level + 1, column, false)?;
+                }
+                self.write_newline(writer, level)?;
+                self.write_token(writer, STRUCTURE, b"e")
+            }
+        }
+    }
+
+    fn write_token<W: io::Write>(
+        &self,
+        writer: &mut W,
+        color: &str,
+        token: &[u8],
+    ) -> io::Result<()> {
+        if self.color {
+            writer.write_all(color.as_bytes())?;
+            writer.write_all(token)?;
+            writer.write_all(RESET.as_bytes())
+        } else {
+            writer.write_all(token)
+        }
+    }
+
+    fn write_newline<W: io::Write>(&self, writer: &mut W, level: usize) -> io::Result<()> {
+        writer.write_all(b"\n")?;
+        for _ in 0..level * self.indent {
+            writer.write_all(b" ")?;
+        }
+        Ok(())
+    }
+}
+
+/// Width of `node` in one line, `None` for non-empty dictionaries that never are.
+fn flat_width(node: &Node) -> Option<usize> {
+    match node {
+        Node::Token(_, token) => Some(token.len()),
+        Node::List(list) if list.is_empty() => Some(2),
+        Node::List(list) => list
+            .iter()
+            .try_fold(1, |width, element| Some(width + 1 + flat_width(element)?))
+            .map(|width| width + 2),
+        Node::Dict(dict) if dict.is_empty() => Some(2),
+        Node::Dict(_) => None,
+    }
+}
+
+/// Reads the next value, `None` for the end of the enclosing list or dictionary.
+fn read_node<'de, R>(tokenizer: &mut Tokenizer<R>) -> Result<Option<Node>>
+where
+    R: read::Read<'de>,
+{
+    let token = match tokenizer.next_token()? {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+    let node = match token.event {
+        Event::End => return Ok(None),
+        Event::Int(digits) => Node::Token(Kind::Int, format!("i{}e", digits).into_bytes()),
+        Event::Str(bytes) => {
+            let mut token = format!("{}:", bytes.len()).into_bytes();
+            token.extend_from_slice(bytes);
+            Node::Token(Kind::Str, token)
+        }
+        Event::Bool(value) => Node::Token(Kind::Bool, if value { b"T" } else { b"F" }.to_vec()),
+        Event::Null => Node::Token(Kind::Null, b"N".to_vec()),
+        Event::ListStart => {
+            let mut list = Vec::new();
+            while let Some(element) = read_node(tokenizer)? {
+                list.push(element);
+            }
+            Node::List(list)
+        }
+        Event::DictStart => {
+            let mut dict = Vec::new();
+            while let Some(key) = read_node(tokenizer)? {
+                // The tokenizer doesn't let a dictionary end between a key and its value.
+                let value = read_node(tokenizer)?.ok_or(ErrorKind::Syntax)?;
+                dict.push((key, value));
+            }
+            Node::Dict(dict)
+        }
+    };
+    Ok(Some(node))
+}
+
+/// Pretty-prints `value` with the default options, see `PrettyPrinter`.
+pub fn to_string_pretty<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    PrettyPrinter::default().to_string(value)
+}
+
+/// Lays out a document with the default options, see `PrettyPrinter`.
+pub fn reformat(input: &str) -> Result<String> {
+    PrettyPrinter::default().reformat(input)
+}
+
+#[test]
+fn test_pretty() {
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Test {
+        name: String,
+        scores: Vec<f64>,
+        tags: Vec<String>,
+        nested: Vec<Vec<u8>>,
+        meta: BTreeMap<String, Option<bool>>,
+        empty: (Vec<u8>, BTreeMap<String, u8>),
+    }
+
+    let mut meta = BTreeMap::new();
+    meta.insert("a".to_owned(), Some(true));
+    meta.insert("b".to_owned(), None);
+    let test = Test {
+        name: "Alice".to_owned(),
+        scores: vec![1.0, -2.5],
+        tags: vec!["x".repeat(35), "y".repeat(35)],
+        nested: vec![vec![1, 2], vec![]],
+        meta,
+        empty: (vec![], BTreeMap::new()),
+    };
+    let expected = "d
+  4:name 5:Alice
+  6:scores l i1e i-2.5e e
+  4:tags l
+    35:xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx
+    35:yyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyy
+  e
+  6:nested l l i1e i2e e le e
+  4:meta d
+    1:a T
+    1:b N
+  e
+  5:empty l le de e
+e";
+    let pretty = to_string_pretty(&test).unwrap();
+    assert_eq!(pretty, expected);
+    let mut de = crate::Deserializer::from_str(&pretty).whitespace(true);
+    assert_eq!(
+        serde::Deserialize::deserialize(&mut de)
+            .map(|t: Test| t)
+            .unwrap(),
+        test
+    );
+    de.end().unwrap();
+    assert!(crate::from_str::<Test>(&pretty).is_err());
+    assert_eq!(reformat(&pretty).unwrap(), expected);
+    assert_eq!(
+        reformat(&crate::to_string(&test).unwrap()).unwrap(),
+        expected
+    );
+
+    let printer = PrettyPrinter::new().indent(1).width(0);
+    assert_eq!(
+        printer.reformat(" l i1e d 1:a le e e ").unwrap(),
+        "l\n i1e\n d\n  1:a le\n e\ne"
+    );
+    assert_eq!(
+        PrettyPrinter::new()
+            .color(true)
+            .reformat("d1:ai1ee")
+            .unwrap(),
+        "\x1b[1md\x1b[0m\n  \x1b[34m1:a\x1b[0m \x1b[36mi1e\x1b[0m\n\x1b[1me\x1b[0m"
+    );
+    assert_eq!(
+        &ErrorKind::TrailingCharacters,
+        reformat("i1ei2e").unwrap_err().kind()
+    );
+    assert_eq!(&ErrorKind::Eof, reformat("li1e").unwrap_err().kind());
+}
@@ -0,0 +1,810 @@
+//! Dynamic representation of any 1code document.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::ops::Index;
+use std::str::FromStr;
+
+use serde::de::{Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::error::Error;
+use crate::number::BigInt;
+
+mod de;
+mod ser;
+
+/// Any 1code value.
+/// |    variant    |  1coded   |
+/// | ------------- | --------- |
+/// | Null          | N         |
+/// | Bool(true)    | T         |
+/// | Int(-1)       | i-1e      |
+/// | BigInt(2^128) | i3402…6e  |
+/// | Float(1.5)    | i1.5e     |
+/// | String("a")   | 1:a       |
+/// | Bytes([0xff]) | 1:\xff    |
+/// | List([Null])  | lNe       |
+/// | Dict({a: 1})  | d1:ai1ee  |
+///
+/// Strings that aren't UTF-8 are read as `Bytes`, integers that don't fit into
+/// `i128` as `BigInt`. Floats are compared and
+/// hashed by their bits, so `Value` can be used as a key.
+///
+/// Dictionary keys are kept as bytes, so keys that aren't UTF-8 read back.
+/// Keys that aren't strings, such as the integers of `di1ei2ee`, fail with
+/// the message of `ErrorKind::KeyMustBeAString`.
+#[derive(Clone, Debug, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i128),
+    BigInt(BigInt),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+static NULL: Value = Value::Null;
+
+/// Converts `value` into a tree without going through the 1code text.
+/// Dictionaries are sorted by key, as in canonical mode of `ser::Serializer`.
+/// A `Decimal` with a fraction becomes the nearest `Value::Float`, which may
+/// lose digits, whole ones become `Value::Int` or `Value::BigInt`.
+pub fn to_value<T>(value: &T) -> crate::Result<Value>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(ser::Serializer)
+}
+
+/// Reads a typed value out of a tree, the inverse of `to_value`.
+pub fn from_value<T>(value: Value) -> crate::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// `None` if the value isn't an integer or doesn't fit into `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(value) => i64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    /// `None` if the value isn't an integer or doesn't fit into `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Int(value) => u64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    /// Integers are converted, possibly with rounding.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(value) => Some(*value),
+            Value::Int(value) => Some(*value as f64),
+            Value::BigInt(value) => value.as_str().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Raw bytes of both `Bytes` and `String`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(value) => Some(value),
+            Value::String(value) => Some(value.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::List(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, Value>> {
+        match self {
+            Value::Dict(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Value of a dictionary by `key`, `None` for other values and missing keys.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_dict().and_then(|dict| dict.get(key.as_bytes()))
+    }
+
+    /// Element of a list by `index`, `None` for other values and out of bounds.
+    pub fn get_index(&self, index: usize) -> Option<&Value> {
+        self.as_list().and_then(|list| list.get(index))
+    }
+
+    /// Position of the variant in the declaration, orders values of different kinds.
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Int(_) | Value::BigInt(_) => 2,
+            Value::Float(_) => 3,
+            Value::String(_) => 4,
+            Value::Bytes(_) => 5,
+            Value::List(_) => 6,
+            Value::Dict(_) => 7,
+        }
+    }
+}
+
+/// `value["key"]` gives `Value::Null` for missing keys and non-dictionaries.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// `value[0]` gives `Value::Null` for indices out of bounds and non-lists.
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::BigInt(a), Value::BigInt(b)) => a.cmp(b),
+            (Value::Int(a), Value::BigInt(b)) => BigInt::from(*a).cmp(b),
+            (Value::BigInt(a), Value::Int(b)) => a.cmp(&BigInt::from(*b)),
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => a.cmp(b),
+            (Value::Dict(a), Value::Dict(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Value::Null => {}
+            Value::Bool(value) => value.hash(state),
+            Value::Int(value) => value.hash(state),
+            // Equal to an `Int` only if it fits into `i128`.
+            Value::BigInt(value) => match i128::try_from(value) {
+                Ok(value) => value.hash(state),
+                Err(_) => value.hash(state),
+            },
+            Value::Float(value) => value.to_bits().hash(state),
+            Value::String(value) => value.hash(state),
+            Value::Bytes(value) => value.hash(state),
+            Value::List(value) => value.hash(state),
+            Value::Dict(value) => value.hash(state),
+        }
+    }
+}
+
+/// Writes the 1code text of the value. Byte strings that aren't UTF-8 are
+/// written lossily, NaN and infinite floats as `iNaNe`, `iinfe` and `i-infe`,
+/// which don't read back. Use `to_vec` to get the exact bytes, it fails for
+/// such floats.
+impl Display for Value {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Float(value) if !value.is_finite() => write!(formatter, "i{}e", value),
+            Value::List(list) => {
+                formatter.write_str("l")?;
+                for element in list {
+                    element.fmt(formatter)?;
+                }
+                formatter.write_str("e")
+            }
+            Value::Dict(dict) => {
+                formatter.write_str("d")?;
+                for (key, value) in dict {
+                    write!(formatter, "{}:{}", key.len(), String::from_utf8_lossy(key))?;
+                    value.fmt(formatter)?;
+                }
+                formatter.write_str("e")
+            }
+            // Every other value has a 1code form.
+            value => {
+                let output = crate::ser::to_vec(value).map_err(|_| fmt::Error)?;
+                formatter.write_str(&String::from_utf8_lossy(&output))
+            }
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::de::from_str(s)
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::Int(i128::from(value))
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8 i16 i32 i64 i128 u8 u16 u32 u64);
+
+impl From<u128> for Value {
+    fn from(value: u128) -> Self {
+        match i128::try_from(value) {
+            Ok(value) => Value::Int(value),
+            Err(_) => Value::BigInt(BigInt::from(value)),
+        }
+    }
+}
+
+/// Gives `Int` if the value fits into `i128`.
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        match i128::try_from(&value) {
+            Ok(value) => Value::Int(value),
+            Err(_) => Value::BigInt(value),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(f64::from(value))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::List(value)
+    }
+}
+
+impl From<BTreeMap<Vec<u8>, Value>> for Value {
+    fn from(value: BTreeMap<Vec<u8>, Value>) -> Self {
+        Value::Dict(value)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Value::Dict(
+            value
+                .into_iter()
+                .map(|(key, value)| (key.into_bytes(), value))
+                .collect(),
+        )
+    }
+}
+
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::Int(value) => {
+                if let Ok(value) = i64::try_from(*value) {
+                    serializer.serialize_i64(value)
+                } else if let Ok(value) = u64::try_from(*value) {
+                    serializer.serialize_u64(value)
+                } else {
+                    serializer.serialize_i128(*value)
+                }
+            }
+            Value::BigInt(value) => value.serialize(serializer),
+            Value::Float(value) => serializer.serialize_f64(*value),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::Bytes(value) => serializer.serialize_bytes(value),
+            Value::List(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for element in list {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            Value::Dict(dict) => {
+                let mut map = serializer.serialize_map(Some(dict.len()))?;
+                for (key, value) in dict {
+                    match std::str::from_utf8(key) {
+                        Ok(key) => map.serialize_entry(key, value)?,
+                        Err(_) => map.serialize_entry(&Bytes(key), value)?,
+                    }
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any 1code value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer)
+    }
+
+    /// `BigInt` digits arrive as a newtype struct of a string.
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::String(digits) => match digits.parse::<BigInt>() {
+                Ok(big_int) => Ok(Value::from(big_int)),
+                Err(_) => Ok(Value::String(digits)),
+            },
+            value => Ok(value),
+        }
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Int(i128::from(value)))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Int(i128::from(value)))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(Value::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(value))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            list.push(element);
+        }
+        Ok(Value::List(list))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = BTreeMap::new();
+        while let Some(key) = map.next_key_seed(KeyVisitor)? {
+            dict.insert(key, map.next_value()?);
+        }
+        Ok(Value::Dict(dict))
+    }
+}
+
+/// Writes a dictionary key that isn't UTF-8 as a byte string.
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Reads a dictionary key as bytes, other keys fail with `KeyMustBeAString`.
+struct KeyVisitor;
+
+impl KeyVisitor {
+    fn error<E: serde::de::Error>(self) -> Result<Vec<u8>, E> {
+        Err(E::custom(crate::ErrorKind::KeyMustBeAString))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for KeyVisitor {
+    type Value = Vec<u8>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string key")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, _value: bool) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, _value: i64) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_i128<E: serde::de::Error>(self, _value: i128) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, _value: u64) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_u128<E: serde::de::Error>(self, _value: u128) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, _value: f64) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Vec<u8>, E> {
+        self.error()
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Vec<u8>, E> {
+        Ok(value.as_bytes().to_vec())
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Vec<u8>, E> {
+        Ok(value.into_bytes())
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(value)
+    }
+
+    fn visit_seq<A>(self, _seq: A) -> Result<Vec<u8>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.error()
+    }
+
+    fn visit_map<A>(self, _map: A) -> Result<Vec<u8>, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.error()
+    }
+}
+
+#[test]
+fn test_value() {
+    let test_1code = "d4:listli1ei-1e1:aN2:пe4:sized1:xi18446744073709551615eee";
+    let value: Value = test_1code.parse().unwrap();
+    assert_eq!(value.to_string(), test_1code);
+    assert_eq!(value["list"][0].as_i64(), Some(1));
+    assert_eq!(value["list"][1].as_u64(), None);
+    assert_eq!(value["list"][2].as_str(), Some("a"));
+    assert!(value["list"][3].is_null());
+    assert_eq!(value["list"][4].as_str(), Some("п"));
+    assert!(value["list"][5].is_null());
+    assert_eq!(value["size"]["x"].as_u64(), Some(u64::MAX));
+    assert!(value["missing"]["x"].is_null());
+
+    let big =
+        "li-170141183460469231731687303715884105729ei340282366920938463463374607431768211455ee";
+    let value: Value = big.parse().unwrap();
+    assert!(matches!(value[0], Value::BigInt(_)));
+    assert_eq!(value[1], Value::from(u128::MAX));
+    assert_eq!(value[1].as_u64(), None);
+    assert_eq!(value.to_string(), big);
+    assert_eq!(crate::to_value(&value).unwrap(), value);
+    assert_eq!(
+        crate::from_value::<Vec<BigInt>>(value.clone()).unwrap()[1],
+        BigInt::from(u128::MAX)
+    );
+    assert_eq!(Value::BigInt(BigInt::from(1)), Value::from(1));
+    assert!(value[0] < Value::from(i128::MIN));
+
+    let bytes: Value = crate::from_slice(b"2:\xff\x00").unwrap();
+    assert_eq!(bytes, Value::Bytes(vec![0xff, 0x00]));
+    assert_eq!(bytes.as_bytes(), Some(&[0xff, 0x00][..]));
+    assert_eq!(crate::to_vec(&bytes).unwrap(), b"2:\xff\x00");
+
+    let list = Value::from(vec![
+        Value::from(true),
+        Value::from(false),
+        Value::from(1.5),
+        Value::Null,
+    ]);
+    assert_eq!(list.to_string(), "lTFi1.5eNe");
+    assert_eq!(list.to_string().parse::<Value>().unwrap(), list);
+    assert_eq!(Value::from(f64::NAN).to_string(), "iNaNe");
+    let infinities = Value::List(vec![
+        Value::from(f64::INFINITY),
+        Value::from(f64::NEG_INFINITY),
+    ]);
+    assert_eq!(infinities.to_string(), "liinfei-infee");
+    assert!(crate::to_vec(&infinities).is_err());
+    // Floats without a fraction are read back as integers, except for `-0.0`.
+    assert_eq!("i1e".parse::<Value>().unwrap(), Value::from(1));
+    assert_eq!(
+        Value::from(-0.0).to_string().parse::<Value>().unwrap(),
+        Value::from(-0.0)
+    );
+}
+
+#[test]
+fn test_value_ord() {
+    use std::collections::{BTreeSet, HashSet};
+
+    let values = vec![
+        Value::from("a"),
+        Value::Null,
+        Value::from(2),
+        Value::from(-1),
+        Value::from(f64::NAN),
+        Value::from(true),
+        Value::from(f64::NAN),
+        Value::from(2),
+    ];
+    let sorted: Vec<Value> = values
+        .iter()
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    assert_eq!(
+        sorted,
+        vec![
+            Value::Null,
+            Value::from(true),
+            Value::from(-1),
+            Value::from(2),
+            Value::from(f64::NAN),
+            Value::from("a"),
+        ]
+    );
+    assert_eq!(values.into_iter().collect::<HashSet<_>>().len(), 6);
+    assert!(Value::from(1) < Value::from(1.0));
+    assert_ne!(Value::from(1), Value::from(1.0));
+}
+
+#[test]
+fn test_to_value() {
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Newtype(u32),
+        Tuple(u32, u32),
+        Struct { a: u32 },
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Request {
+        id: u64,
+        price: f64,
+        tags: Vec<String>,
+        #[serde(with = "serde_bytes")]
+        hash: Vec<u8>,
+        parent: Option<Box<Request>>,
+        kinds: Vec<E>,
+    }
+
+    let request = Request {
+        id: u64::MAX,
+        price: -1.5,
+        tags: vec!["a".to_owned()],
+        hash: vec![0xff],
+        parent: Some(Box::new(Request {
+            id: 0,
+            price: 0.0,
+            tags: vec![],
+            hash: vec![],
+            parent: None,
+            kinds: vec![],
+        })),
+        kinds: vec![E::Unit, E::Newtype(1), E::Tuple(1, 2), E::Struct { a: 1 }],
+    };
+    let mut value = to_value(&request).unwrap();
+    assert_eq!(value["id"].as_u64(), Some(u64::MAX));
+    assert_eq!(value["parent"]["parent"], Value::Null);
+    assert_eq!(value["kinds"][0].as_str(), Some("Unit"));
+    assert_eq!(
+        crate::to_vec(&value).unwrap(),
+        crate::to_vec_canonical(&request).unwrap()
+    );
+    assert_eq!(from_value::<Request>(value.clone()).unwrap(), request);
+
+    if let Value::Dict(dict) = &mut value {
+        dict.insert(b"id".to_vec(), Value::from(7));
+    }
+    assert_eq!(from_value::<Request>(value).unwrap().id, 7);
+
+    let mut integer_keys = BTreeMap::new();
+    integer_keys.insert(1, 1);
+    assert_eq!(
+        &crate::ErrorKind::KeyMustBeAString,
+        to_value(&integer_keys).unwrap_err().kind()
+    );
+    assert!(from_value::<u8>(Value::from("a")).is_err());
+    assert!(from_value::<u8>(Value::from(256)).is_err());
+
+    let precise: crate::number::Decimal = "0.1000000000000000000001".parse().unwrap();
+    assert_eq!(to_value(&precise).unwrap(), Value::Float(0.1));
+    let whole: crate::number::Decimal = "100000000000000000000".parse().unwrap();
+    let big_int: BigInt = "100000000000000000000".parse().unwrap();
+    assert_eq!(to_value(&whole).unwrap(), Value::from(big_int));
+
+    let binary_keys: Value = crate::from_slice(b"d2:\xff\xffi1e1:ai2ee").unwrap();
+    assert_eq!(
+        binary_keys.as_dict().unwrap()[&b"\xff\xff"[..]],
+        Value::from(1)
+    );
+    assert_eq!(binary_keys["a"], Value::from(2));
+    assert_eq!(
+        crate::to_vec_canonical(&binary_keys).unwrap(),
+        b"d1:ai2e2:\xff\xffi1ee"
+    );
+    assert_eq!(
+        from_value::<Value>(binary_keys.clone()).unwrap(),
+        binary_keys
+    );
+    let token_key = "d25:$onecode::private::BigInt3:123e";
+    let value: Value = crate::from_str(token_key).unwrap();
+    assert_eq!(value["$onecode::private::BigInt"], Value::from("123"));
+    assert_eq!(crate::to_string(&value).unwrap(), token_key);
+    let error = crate::from_str::<Value>("di1ei2ee").unwrap_err();
+    assert_eq!(
+        error.kind(),
+        &crate::ErrorKind::Message("dictionary key must be a string".to_owned())
+    );
+}